@@ -1,25 +1,18 @@
-use file_rotate::compression::Compression;
-use file_rotate::suffix::{AppendTimestamp, FileLimit};
-use file_rotate::{ContentLimit, FileRotate};
-use handsome_logger::{ConfigBuilder, WriteLogger};
+use handsome_logger::{ConfigBuilder, FileConfigBuilder, FileLogger};
 use log::*;
 
 fn main() {
     let write_config = ConfigBuilder::new()
         .set_format_text("[[_level]] \"[_msg]\" [[_module]] - [_file_name]:[_line]", None)
-        .set_write_once(true) // Use this when rotating files
         .set_level(LevelFilter::Trace)
         .build();
 
-    let write_rotater = FileRotate::new(
-        "my_rust_binary.log",
-        AppendTimestamp::default(FileLimit::MaxFiles(10)),
-        ContentLimit::BytesSurpassed(200),
-        Compression::None,
-        None,
-    );
+    let file_config = FileConfigBuilder::new()
+        .set_max_size(Some(200))
+        .set_archive_pattern("[_file_name].[_time]")
+        .build();
 
-    WriteLogger::init(write_config, write_rotater).unwrap();
+    FileLogger::init(write_config, "my_rust_binary.log", file_config).unwrap();
     for _ in 0..1 {
         trace!("Got TRACE");
         debug!("Got DEBUG");
@@ -29,27 +22,15 @@ fn main() {
     }
 }
 
-// With set_write_once set at false(default value), 2 files will be created with such content:
+// 2 files will be created with such content:
 // - my_rust_binary.log
-// :26
-// [WARN] "Got WARNING" [saving_logs_to_file_with_rotating] - main.rs:27
-// [ERROR] "Got ERROR" [saving_logs_to_file_with_rotating] - main.rs:28
+// [WARN] "Got WARNING" [saving_logs_to_file_with_rotating] - main.rs:20
+// [ERROR] "Got ERROR" [saving_logs_to_file_with_rotating] - main.rs:21
 //
-// - my_rust_binary.log.20230704T220727 - rotated file
-// [TRACE] "Got TRACE" [saving_logs_to_file_with_rotating] - main.rs:24
-// [DEBUG] "Got DEBUG" [saving_logs_to_file_with_rotating] - main.rs:25
-// [INFO] "Got INFO" [saving_logs_to_file_with_rotating] - main.rs
-
-// As you can see, one info!() message is split between 2 files. Because ContentLimit::BytesSurpassed
-// split content when write occurs. To avoid this, set set_write_once to true, which will write
-// content to the file only once per message.
+// - my_rust_binary.log.2023-07-04_22-07-27 - rotated file
+// [TRACE] "Got TRACE" [saving_logs_to_file_with_rotating] - main.rs:17
+// [DEBUG] "Got DEBUG" [saving_logs_to_file_with_rotating] - main.rs:18
+// [INFO] "Got INFO" [saving_logs_to_file_with_rotating] - main.rs:19
 
-// With set_write_once set at true, 2 files will be created with such content:
-// - my_rust_binary.log
-// [WARN] "Got WARNING" [saving_logs_to_file_with_rotating] - main.rs:27
-// [ERROR] "Got ERROR" [saving_logs_to_file_with_rotating] - main.rs:28
-//
-// - my_rust_binary.log.20230704T220727 - rotated file
-// [TRACE] "Got TRACE" [saving_logs_to_file_with_rotating] - main.rs:24
-// [DEBUG] "Got DEBUG" [saving_logs_to_file_with_rotating] - main.rs:25
-// [INFO] "Got INFO" [saving_logs_to_file_with_rotating] - main.rs:26
+// Each message is formatted in memory before writing, so it is never split between 2 files.
+// File can also be rotated every hour or day with `set_rotation_period`.
@@ -1,13 +1,112 @@
-use log::LevelFilter;
+use log::{Level, LevelFilter};
 use std::env;
 
-pub fn get_env_log() -> Option<LevelFilter> {
-    match env::var("RUST_LOG").ok()?.to_lowercase().as_str() {
-        "err" | "error" => Some(LevelFilter::Error),
-        "warn" | "warning" => Some(LevelFilter::Warn),
-        "info" => Some(LevelFilter::Info),
-        "debug" => Some(LevelFilter::Debug),
-        "trace" => Some(LevelFilter::Trace),
-        _ => None,
+use crate::env_filter::EnvFilter;
+
+/// Reads level from environment variable, if any is set
+pub fn get_env_log(env_var: Option<&str>) -> Option<LevelFilter> {
+    env_var.and_then(EnvFilter::from_env).map(|filter| filter.max_level())
+}
+
+/// Severity used by syslog and journald, defined in RFC 5424
+/// Trace has no own severity, so it is logged as debug
+pub(crate) fn syslog_severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Name of current machine, or "localhost" if it cannot be read
+pub(crate) fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // SAFETY: buffer is valid for its whole length and one byte is left for null terminator
+        if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len() - 1) } == 0 {
+            let length = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+            if length > 0 {
+                return String::from_utf8_lossy(&buffer[..length]).into_owned();
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if let Ok(hostname) = env::var("COMPUTERNAME") {
+            return hostname;
+        }
+    }
+    "localhost".to_string()
+}
+
+/// Name of current executable without extension, used to identify application in system logs
+pub(crate) fn app_name() -> String {
+    env::current_exe()
+        .ok()
+        .and_then(|path| path.file_stem().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "rust".to_string())
+}
+
+/// Empty directory unique for test, removed with its content when dropped
+#[cfg(test)]
+pub(crate) struct TestDir(pub(crate) std::path::PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("handsome_logger_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
     }
 }
+
+/// Write target collecting written bytes, which can be cloned and read by test
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(pub(crate) std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl SharedBuffer {
+    pub(crate) fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+
+    pub(crate) fn lines(&self) -> Vec<String> {
+        self.text().lines().map(str::to_string).collect()
+    }
+
+    /// Returns written text and clears buffer
+    pub(crate) fn take(&self) -> String {
+        String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Config builder for tests, which ignores `RUST_LOG`, so results don't depend on environment
+#[cfg(test)]
+pub(crate) fn test_config_builder() -> crate::ConfigBuilder {
+    let mut builder = crate::ConfigBuilder::new();
+    builder.set_env_var(None);
+    builder
+}
@@ -0,0 +1,261 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use crate::retention::ArchiveMatcher;
+
+pub(crate) const TEMPORARY_SUFFIX: &str = ".tmp";
+/// Suffixes of all supported compressions, even disabled ones, to recognize archives made by other builds
+pub(crate) const COMPRESSION_SUFFIXES: [&str; 2] = [".gz", ".zst"];
+
+/// Compression of rotated log files
+/// Each compression is available only with cargo feature of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Suffix added to name of compressed archive
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::None => "",
+            #[cfg(feature = "gzip")]
+            Self::Gzip => ".gz",
+            #[cfg(feature = "zstd")]
+            Self::Zstd => ".zst",
+        }
+    }
+}
+
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path: OsString = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Removes compression and temporary file suffixes from archive name
+pub(crate) fn strip_archive_suffixes(name: &str) -> &str {
+    let name = name.strip_suffix(TEMPORARY_SUFFIX).unwrap_or(name);
+    strip_compression_suffix(name).unwrap_or(name)
+}
+
+fn strip_compression_suffix(name: &str) -> Option<&str> {
+    COMPRESSION_SUFFIXES.iter().find_map(|suffix| name.strip_suffix(suffix))
+}
+
+/// Compresses file into temporary file, then renames it and removes original
+/// If process is killed in the middle, only temporary file is left, which is cleaned up by `recover`
+pub(crate) fn compress_file(path: &Path, compression: Compression) -> io::Result<()> {
+    let compressed = with_suffix(path, compression.suffix());
+    let temporary = with_suffix(&compressed, TEMPORARY_SUFFIX);
+
+    let result = write_compressed(path, &temporary, compression).and_then(|()| fs::rename(&temporary, &compressed));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+        return result;
+    }
+    fs::remove_file(path)
+}
+
+fn write_compressed(path: &Path, temporary: &Path, compression: Compression) -> io::Result<()> {
+    let mut input = File::open(path)?;
+    let mut output = File::create(temporary)?;
+
+    let output = match compression {
+        Compression::None => {
+            io::copy(&mut input, &mut output)?;
+            output
+        }
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let mut encoder = zstd::Encoder::new(output, 0)?;
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+    };
+    output.sync_all()
+}
+
+/// Cleans up after process which was stopped while compressing archives
+/// Returns archives that still need to be compressed
+pub(crate) fn recover(path: &Path, pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let matcher = ArchiveMatcher::new(path, pattern);
+
+    let mut not_compressed = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if let Some(compressed) = name.strip_suffix(TEMPORARY_SUFFIX) {
+            // Partially written archive, original file still exists and will be compressed again
+            if strip_compression_suffix(compressed).is_some_and(|original| matcher.matches_uncompressed(original)) {
+                fs::remove_file(entry.path())?;
+            }
+        } else if let Some(original) = strip_compression_suffix(&name) {
+            // Compressed file is complete, because it is renamed only after writing whole content
+            let original_path = directory.join(original);
+            if matcher.matches_uncompressed(original) && original_path.exists() {
+                fs::remove_file(original_path)?;
+            }
+        } else if matcher.matches_uncompressed(&name) {
+            not_compressed.push(entry.path());
+        }
+    }
+    not_compressed.retain(|path| path.exists());
+    not_compressed.sort();
+    Ok(not_compressed)
+}
+
+/// Background thread which compresses archives, so logging thread is not blocked by it
+pub(crate) struct Compressor {
+    sender: Mutex<Option<Sender<PathBuf>>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Compressor {
+    /// Starts compression thread, which first finishes work left by previous run of application
+    /// Returns none if compression is disabled
+    pub(crate) fn start(path: &Path, pattern: &'static str, compression: Compression) -> io::Result<Option<Self>> {
+        if compression == Compression::None {
+            return Ok(None);
+        }
+
+        let (sender, receiver) = channel::<PathBuf>();
+        let path = path.to_path_buf();
+        let worker = thread::Builder::new().name("handsome_logger_compression".to_string()).spawn(move || {
+            for archive in recover(&path, pattern).unwrap_or_default() {
+                let _ = compress_file(&archive, compression);
+            }
+            for archive in receiver {
+                let _ = compress_file(&archive, compression);
+            }
+        })?;
+
+        Ok(Some(Self {
+            sender: Mutex::new(Some(sender)),
+            worker: Mutex::new(Some(worker)),
+        }))
+    }
+
+    pub(crate) fn compress(&self, archive: PathBuf) {
+        if let Some(sender) = &*self.sender.lock().unwrap() {
+            let _ = sender.send(archive);
+        }
+    }
+
+    /// Waits until all queued archives are compressed and stops thread, archives created later are
+    /// not compressed
+    pub(crate) fn finish(&self) {
+        self.sender.lock().unwrap().take();
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Compressor {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(all(test, any(feature = "gzip", feature = "zstd")))]
+mod tests {
+    use std::io::{Read, Write};
+
+    use crate::common::TestDir;
+
+    use super::*;
+
+    fn enabled_compressions() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "gzip")]
+            Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+        ]
+    }
+
+    fn decompress(path: &Path, compression: Compression) -> String {
+        let file = File::open(path).unwrap();
+        let mut content = String::new();
+        match compression {
+            Compression::None => unreachable!(),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => flate2::read::GzDecoder::new(file).read_to_string(&mut content).unwrap(),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::Decoder::new(file).unwrap().read_to_string(&mut content).unwrap(),
+        };
+        content
+    }
+
+    #[test]
+    fn test_compress_file() {
+        for compression in enabled_compressions() {
+            let dir = TestDir::new(&format!("compress_file{}", compression.suffix()));
+            let archive = dir.0.join("app.log.1");
+            fs::write(&archive, "Some log\n".repeat(100)).unwrap();
+
+            compress_file(&archive, compression).unwrap();
+
+            assert!(!archive.exists());
+            assert!(!with_suffix(&archive, &format!("{}{TEMPORARY_SUFFIX}", compression.suffix())).exists());
+            assert_eq!(decompress(&with_suffix(&archive, compression.suffix()), compression), "Some log\n".repeat(100));
+        }
+    }
+
+    #[test]
+    fn test_recover_after_interrupted_compression() {
+        let compression = enabled_compressions()[0];
+        let suffix = compression.suffix();
+        let dir = TestDir::new("compression_recover");
+        let path = dir.0.join("app.log");
+        fs::write(&path, "current").unwrap();
+        // Killed while writing compressed file
+        fs::write(dir.0.join("app.log.1"), "first").unwrap();
+        fs::write(with_suffix(&dir.0.join("app.log.1"), &format!("{suffix}{TEMPORARY_SUFFIX}")), "broken").unwrap();
+        // Killed after rename, before removing original
+        fs::write(dir.0.join("app.log.2"), "second").unwrap();
+        let mut compressed = File::create(with_suffix(&dir.0.join("app.log.2"), suffix)).unwrap();
+        compressed.write_all(b"compressed").unwrap();
+        // Killed before compression started
+        fs::write(dir.0.join("app.log.3"), "third").unwrap();
+        // Files not created by logger
+        let foreign = ["app.log.bak", "app.log.bak.tmp", "app.log.tmp", "app.log.1.tmp", "app.log.debug.gz", "other.log.4"];
+        for name in foreign {
+            fs::write(dir.0.join(name), "foreign").unwrap();
+        }
+
+        let not_compressed = recover(&path, "[_file_name].[_index]").unwrap();
+
+        assert_eq!(not_compressed, vec![dir.0.join("app.log.1"), dir.0.join("app.log.3")]);
+        assert!(!dir.0.join("app.log.2").exists());
+        assert!(with_suffix(&dir.0.join("app.log.2"), suffix).exists());
+        assert!(!with_suffix(&dir.0.join("app.log.1"), &format!("{suffix}{TEMPORARY_SUFFIX}")).exists());
+        assert!(path.exists());
+        for name in foreign {
+            assert!(dir.0.join(name).exists(), "{name}");
+        }
+    }
+}
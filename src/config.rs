@@ -1,5 +1,5 @@
 use core::fmt::{Debug, Formatter};
-use log::{LevelFilter, Record};
+use log::{LevelFilter, Metadata, Record};
 use std::io::{Error, Write};
 use std::sync::Arc;
 use termcolor::{BufferedStandardStream, Color, ColorSpec};
@@ -7,6 +7,8 @@ pub use time::format_description::FormatItem;
 pub use time::macros::format_description;
 pub use time::UtcOffset;
 
+use crate::env_filter::EnvFilter;
+
 const LEVEL_NUMBER: usize = 6;
 
 #[derive(Debug, Clone, Copy)]
@@ -19,6 +21,73 @@ pub enum TimeFormat {
     Custom(&'static [FormatItem<'static>]),
 }
 
+/// Kind of thread id printed by `[_thread_id]` token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ThreadIdKind {
+    /// Id given by Rust standard library, starts from 1 for main thread
+    #[default]
+    Rust,
+    /// Kernel thread id(TID), the same that is visible in `top`, `perf` or `gdb`
+    /// Only available on Linux and Android, on other platforms Rust id is used
+    Native,
+}
+
+/// Format of records written by loggers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum OutputFormat {
+    /// Text created from format text and its tokens
+    #[default]
+    Text,
+    /// One JSON object per line, with all key-value pairs of record
+    Json,
+    /// One logfmt line per record e.g. `ts=... level=info module=app msg="Hello world" user=john`
+    /// In `TermLogger` keys are colored with color of level
+    Logfmt,
+}
+
+/// Field of JSON object, whose name can be changed with `set_json_field_name`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JsonField {
+    /// Time of log call in RFC 3339 format, default name `timestamp`
+    Timestamp,
+    /// Level in uppercase, default name `level`
+    Level,
+    /// Target of record, default name `target`
+    Target,
+    /// Module path, default name `module`
+    Module,
+    /// Full path of source file, default name `file`
+    File,
+    /// Line in source file, default name `line`
+    Line,
+    /// Thread id of kind set by `set_thread_id_kind`, default name `thread_id`
+    ThreadId,
+    /// Thread name, default name `thread_name`
+    ThreadName,
+    /// Logged message, default name `message`
+    Message,
+}
+
+/// Set of JSON fields expected by log collectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum JsonSchema {
+    /// Fields with names set by `set_json_field_name`
+    #[default]
+    Default,
+    /// Elastic Common Schema - `@timestamp`, `log.level`, `message`, `log.origin.*` etc.
+    Ecs,
+    /// Google Cloud Logging - `timestamp`, `severity`, `message` and `logging.googleapis.com/sourceLocation`
+    Gcp,
+    /// Bunyan - numeric `level`, `v`, `name`, `hostname`, `pid`, `time` and `msg`
+    Bunyan,
+}
+
+pub(crate) const JSON_FIELD_NUMBER: usize = 9;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Token {
     Text(&'static str),
@@ -43,8 +112,17 @@ type TerminalWriteFunction = dyn Fn(&Record, &mut BufferedStandardStream) -> Res
 #[derive(Clone)]
 pub struct Config {
     pub(crate) level: LevelFilter,
+    pub(crate) env_var: Option<&'static str>,
     pub(crate) time_offset: UtcOffset,
     pub(crate) write_once: bool,
+    pub(crate) thread_id_kind: ThreadIdKind,
+    pub(crate) systemd_priority_prefix: bool,
+    pub(crate) flush_every_record: bool,
+
+    pub(crate) output_format: OutputFormat,
+    pub(crate) json_field_names: [Option<&'static str>; JSON_FIELD_NUMBER],
+    pub(crate) json_key_values_field: Option<&'static str>,
+    pub(crate) json_schema: JsonSchema,
 
     pub(crate) time_format: [TimeFormat; LEVEL_NUMBER],
     pub(crate) format_text: [&'static str; LEVEL_NUMBER],
@@ -57,6 +135,7 @@ pub struct Config {
     pub(crate) enabled_colors: bool,
 
     pub(crate) message_filtering: Option<Arc<FilterFunction>>,
+    pub(crate) env_filter: Option<Arc<EnvFilter>>,
     pub(crate) write_formatter: Option<Arc<WriteFunction>>,
     pub(crate) terminal_formatter: Option<Arc<TerminalWriteFunction>>,
 }
@@ -108,6 +187,20 @@ impl Config {
     pub(crate) fn calculate_data(&mut self) {
         self.calculate_tokens();
         self.calculate_colors();
+        if let Some(env_var) = self.env_var {
+            self.env_filter = EnvFilter::from_env(env_var).map(Arc::new);
+        }
+    }
+
+    /// Checks if target and level of record are allowed by directives from environment variable
+    pub(crate) fn env_enabled(&self, metadata: &Metadata) -> bool {
+        self.env_filter.as_ref().is_none_or(|env_filter| env_filter.enabled(metadata))
+    }
+
+    /// Checks if message matches filter set after `/` in environment variable
+    /// Formats message when it is not static, so it should be checked only for enabled records
+    pub(crate) fn env_matches_message(&self, record: &Record) -> bool {
+        self.env_filter.as_ref().is_none_or(|env_filter| env_filter.matches_message(record))
     }
 
     /// Creating `ColorSpec` from user colors
@@ -237,6 +330,15 @@ impl ConfigBuilder {
         self
     }
 
+    /// Sets environment variable, from which level and filter directives are read, `RUST_LOG` by default
+    /// Level from variable overrides level set in config
+    /// Using different variables, e.g. `MYAPP_FILE_LOG`, allows to control each logger in `CombinedLogger` separately
+    /// If variable is none, environment is ignored and level from config is always used
+    pub fn set_env_var(&mut self, env_var: Option<&'static str>) -> &mut Self {
+        self.0.env_var = env_var;
+        self
+    }
+
     /// Instead of writing multiple times to target, creates a buffer, writes to memory and
     /// at the end writes only once to target
     /// This is useful when saving to file, because allows to not split one log into multiple
@@ -247,6 +349,61 @@ impl ConfigBuilder {
         self
     }
 
+    /// Sets which thread id is printed by `[_thread_id]` token
+    /// By default Rust thread id is used
+    pub fn set_thread_id_kind(&mut self, thread_id_kind: ThreadIdKind) -> &mut Self {
+        self.0.thread_id_kind = thread_id_kind;
+        self
+    }
+
+    /// Prefixes each record written as text line e.g. by `TermLogger` or `WriteLogger` with systemd
+    /// priority e.g. `<3>` for errors, so journald assigns proper priority to lines of service
+    /// without using journald library
+    /// When enabled, colors are not printed, even if `enabled_colours` is true
+    pub fn set_systemd_priority_prefix(&mut self, systemd_priority_prefix: bool) -> &mut Self {
+        self.0.systemd_priority_prefix = systemd_priority_prefix;
+        self
+    }
+
+    /// Flushes terminal after each record printed by `TermLogger`, enabled by default
+    /// When disabled, output is written in bigger blocks, which is faster, but records still kept
+    /// in buffer are lost at exit, unless logger is flushed e.g. by `LoggerGuard`
+    /// Used only by `TermLogger`, other loggers write records to their outputs directly, so e.g.
+    /// `WriteLogger` with buffered writer is flushed only by `flush` or `shutdown`
+    pub fn set_flush_every_record(&mut self, flush_every_record: bool) -> &mut Self {
+        self.0.flush_every_record = flush_every_record;
+        self
+    }
+
+    /// Sets format of written records, by default text created from format text is used
+    /// Format text and time format are ignored when JSON or logfmt is used
+    pub fn set_output_format(&mut self, output_format: OutputFormat) -> &mut Self {
+        self.0.output_format = output_format;
+        self
+    }
+
+    /// Sets schema of JSON output, so it can be read by log collector without additional processing
+    /// Field names set by `set_json_field_name` are used only by default schema
+    pub fn set_json_schema(&mut self, json_schema: JsonSchema) -> &mut Self {
+        self.0.json_schema = json_schema;
+        self
+    }
+
+    /// Sets name of field in JSON output
+    /// If name is none, field is not written
+    pub fn set_json_field_name(&mut self, field: JsonField, name: Option<&'static str>) -> &mut Self {
+        self.0.json_field_names[field as usize] = name;
+        self
+    }
+
+    /// Sets name of JSON object, which contains key-value pairs of record
+    /// If name is none(default), pairs are written directly into main object and keys already used
+    /// by schema, like `message`, are prefixed with `fields.`
+    pub fn set_json_key_values_field(&mut self, name: Option<&'static str>) -> &mut Self {
+        self.0.json_key_values_field = name;
+        self
+    }
+
     /// Set time format used in logger
     /// If level is none, it will set all levels
     /// Time format can be predefined(Rfc2822 or Rfc3339) or custom
@@ -391,7 +548,25 @@ impl Default for Config {
 
         Self {
             level: LevelFilter::Info,
+            env_var: Some("RUST_LOG"),
             write_once: false,
+            thread_id_kind: ThreadIdKind::default(),
+            systemd_priority_prefix: false,
+            flush_every_record: true,
+            output_format: OutputFormat::default(),
+            json_field_names: [
+                Some("timestamp"),
+                Some("level"),
+                Some("target"),
+                Some("module"),
+                Some("file"),
+                Some("line"),
+                Some("thread_id"),
+                Some("thread_name"),
+                Some("message"),
+            ],
+            json_key_values_field: None,
+            json_schema: JsonSchema::default(),
             time_format: [TimeFormat::TimeWithMicro; LEVEL_NUMBER],
             time_offset: tz_offset,
 
@@ -410,6 +585,7 @@ impl Default for Config {
             format_text: [FormatText::DefaultC.get(); LEVEL_NUMBER],
             compiled_colors: [ColorSpec::new(), ColorSpec::new(), ColorSpec::new(), ColorSpec::new(), ColorSpec::new(), ColorSpec::new()],
             message_filtering: None,
+            env_filter: None,
             write_formatter: None,
             terminal_formatter: None,
         }
@@ -420,7 +596,15 @@ impl Debug for Config {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         f.debug_struct("Config")
             .field("level", &self.level)
+            .field("env_var", &self.env_var)
             .field("write_once", &self.write_once)
+            .field("thread_id_kind", &self.thread_id_kind)
+            .field("systemd_priority_prefix", &self.systemd_priority_prefix)
+            .field("flush_every_record", &self.flush_every_record)
+            .field("output_format", &self.output_format)
+            .field("json_field_names", &self.json_field_names)
+            .field("json_key_values_field", &self.json_key_values_field)
+            .field("json_schema", &self.json_schema)
             .field("time_format", &self.time_format)
             .field("time_offset", &self.time_offset)
             .field("tokens", &self.tokens)
@@ -429,6 +613,7 @@ impl Debug for Config {
             .field("enabled_colors", &self.enabled_colors)
             .field("format_text", &self.format_text)
             .field("compiled_colors", &self.compiled_colors)
+            .field("env_filter", &self.env_filter)
             .finish_non_exhaustive()
     }
 }
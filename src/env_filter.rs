@@ -0,0 +1,228 @@
+use std::env;
+
+use log::{LevelFilter, Metadata, Record};
+
+/// Level set for all targets, which start with given text, or for all targets if target is none
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directive {
+    target: Option<String>,
+    level: LevelFilter,
+}
+
+/// Filter of messages, written after `/` in directives
+#[derive(Debug, Clone)]
+enum MessageFilter {
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+    #[cfg(not(feature = "regex"))]
+    Substring(String),
+}
+
+impl MessageFilter {
+    fn new(pattern: &str) -> Option<Self> {
+        #[cfg(feature = "regex")]
+        {
+            regex::Regex::new(pattern).ok().map(Self::Regex)
+        }
+        #[cfg(not(feature = "regex"))]
+        {
+            Some(Self::Substring(pattern.to_string()))
+        }
+    }
+
+    fn is_match(&self, message: &str) -> bool {
+        match self {
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => regex.is_match(message),
+            #[cfg(not(feature = "regex"))]
+            Self::Substring(text) => message.contains(text.as_str()),
+        }
+    }
+}
+
+/// Levels and message filter read from environment variable, in format used by `env_logger`
+/// e.g. `warn,my_app=debug,my_app::network=trace,hyper=off/timeout`
+/// Target uses level of the longest directive, whose name is prefix of target, targets not matching
+/// any directive are not logged
+/// Text after `/` is regex(with `regex` feature) or text(without it), which must be found in message
+#[derive(Debug, Clone)]
+pub(crate) struct EnvFilter {
+    directives: Vec<Directive>,
+    message_filter: Option<MessageFilter>,
+}
+
+impl EnvFilter {
+    /// Reads directives from environment variable
+    /// Returns none if variable is not set or doesn't contain any valid directive
+    pub(crate) fn from_env(variable: &str) -> Option<Self> {
+        Self::parse(&env::var(variable).ok()?)
+    }
+
+    pub(crate) fn parse(spec: &str) -> Option<Self> {
+        let (directives_text, filter_text) = match spec.split_once('/') {
+            Some((directives_text, filter_text)) => (directives_text, Some(filter_text)),
+            None => (spec, None),
+        };
+
+        let mut directives = Vec::new();
+        for part in directives_text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let mut parts = part.split('=');
+            let directive = match (parts.next(), parts.next(), parts.next()) {
+                (Some(name), None, None) => match parse_level(name) {
+                    Some(level) => Directive { target: None, level },
+                    None => Directive {
+                        target: Some(name.to_string()),
+                        level: LevelFilter::Trace,
+                    },
+                },
+                (Some(name), Some(level), None) => match parse_level(level) {
+                    Some(level) if name.is_empty() => Directive { target: None, level },
+                    Some(level) => Directive {
+                        target: Some(name.to_string()),
+                        level,
+                    },
+                    None => continue,
+                },
+                _ => continue,
+            };
+            directives.push(directive);
+        }
+
+        let message_filter = filter_text.and_then(MessageFilter::new);
+        if directives.is_empty() {
+            message_filter.as_ref()?;
+            // The same default as in env_logger, when only filter is given
+            directives.push(Directive {
+                target: None,
+                level: LevelFilter::Error,
+            });
+        }
+
+        // Later directives for the same target overwrite earlier ones
+        let mut unique_directives: Vec<Directive> = Vec::with_capacity(directives.len());
+        for directive in directives {
+            unique_directives.retain(|existing| existing.target != directive.target);
+            unique_directives.push(directive);
+        }
+        unique_directives.sort_by_key(|directive| directive.target.as_ref().map_or(0, String::len));
+
+        Some(Self {
+            directives: unique_directives,
+            message_filter,
+        })
+    }
+
+    /// The most verbose level allowed by any directive
+    pub(crate) fn max_level(&self) -> LevelFilter {
+        self.directives.iter().map(|directive| directive.level).max().unwrap_or(LevelFilter::Off)
+    }
+
+    /// Level allowed for target, taken from the most specific matching directive
+    pub(crate) fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .rev()
+            .find(|directive| directive.target.as_deref().is_none_or(|name| target.starts_with(name)))
+            .map_or(LevelFilter::Off, |directive| directive.level)
+    }
+
+    pub(crate) fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    /// Checks if message matches filter given after `/`
+    pub(crate) fn matches_message(&self, record: &Record) -> bool {
+        match &self.message_filter {
+            Some(message_filter) => match record.args().as_str() {
+                Some(message) => message_filter.is_match(message),
+                None => message_filter.is_match(&record.args().to_string()),
+            },
+            None => true,
+        }
+    }
+}
+
+/// Parses level name(case insensitive) or number, where 0 means `off` and 5 `trace`
+fn parse_level(text: &str) -> Option<LevelFilter> {
+    match text.trim().to_lowercase().as_str() {
+        "off" | "0" => Some(LevelFilter::Off),
+        "err" | "error" | "1" => Some(LevelFilter::Error),
+        "warn" | "warning" | "2" => Some(LevelFilter::Warn),
+        "info" | "3" => Some(LevelFilter::Info),
+        "debug" | "4" => Some(LevelFilter::Debug),
+        "trace" | "5" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    fn enabled(filter: &EnvFilter, target: &str, level: Level) -> bool {
+        filter.enabled(&Metadata::builder().target(target).level(level).build())
+    }
+
+    #[test]
+    fn test_global_levels() {
+        for (spec, level) in [
+            ("info", LevelFilter::Info),
+            ("WARN", LevelFilter::Warn),
+            ("err", LevelFilter::Error),
+            ("off", LevelFilter::Off),
+            ("4", LevelFilter::Debug),
+            ("0", LevelFilter::Off),
+        ] {
+            let filter = EnvFilter::parse(spec).unwrap();
+            assert_eq!(filter.max_level(), level, "{spec}");
+            assert_eq!(filter.level_for("any::target"), level, "{spec}");
+        }
+        assert!(EnvFilter::parse("").is_none());
+        assert!(EnvFilter::parse("my_app=loud").is_none());
+    }
+
+    #[test]
+    fn test_target_directives() {
+        let filter = EnvFilter::parse("warn,my_app=debug,my_app::network=trace,hyper=off,tokio,bad=level").unwrap();
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+        assert!(enabled(&filter, "other", Level::Warn));
+        assert!(!enabled(&filter, "other", Level::Info));
+        assert!(enabled(&filter, "my_app::db", Level::Debug));
+        assert!(!enabled(&filter, "my_app::db", Level::Trace));
+        assert!(enabled(&filter, "my_app::network::http", Level::Trace));
+        assert!(!enabled(&filter, "hyper::client", Level::Error));
+        assert!(enabled(&filter, "tokio::runtime", Level::Trace));
+        assert!(!enabled(&filter, "bad", Level::Info));
+
+        // Without default level, other targets are disabled
+        let filter = EnvFilter::parse("my_app=info").unwrap();
+        assert!(enabled(&filter, "my_app", Level::Info));
+        assert!(!enabled(&filter, "other", Level::Error));
+
+        // Last directive for the same target wins
+        let filter = EnvFilter::parse("my_app=info,my_app=error").unwrap();
+        assert!(!enabled(&filter, "my_app", Level::Warn));
+    }
+
+    #[test]
+    fn test_message_filter() {
+        let filter = EnvFilter::parse("info/time.*out").unwrap();
+        let matches = |message: &str| filter.matches_message(&Record::builder().args(format_args!("{message}")).build());
+
+        #[cfg(feature = "regex")]
+        {
+            assert!(matches("timed out"));
+            assert!(!matches("time"));
+        }
+        #[cfg(not(feature = "regex"))]
+        {
+            assert!(matches("error: time.*out"));
+            assert!(!matches("timed out"));
+        }
+
+        let filter = EnvFilter::parse("/connection").unwrap();
+        assert_eq!(filter.max_level(), LevelFilter::Error);
+    }
+}
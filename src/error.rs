@@ -0,0 +1,34 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+
+use log::SetLoggerError;
+
+/// Error returned by `init` of loggers, which need to open file or socket before logging
+#[derive(Debug)]
+pub enum InitError {
+    Io(io::Error),
+    SetLogger(SetLoggerError),
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Failed to open log output: {err}"),
+            Self::SetLogger(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
+impl From<io::Error> for InitError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<SetLoggerError> for InitError {
+    fn from(err: SetLoggerError) -> Self {
+        Self::SetLogger(err)
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::Path;
+
+use time::{Duration, OffsetDateTime, Time};
+
+use crate::compression::Compression;
+
+/// Period after which `FileLogger` moves current log file to archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RotationPeriod {
+    Hourly,
+    Daily,
+}
+
+impl RotationPeriod {
+    /// Returns first period boundary after given time, e.g. next full hour
+    pub(crate) fn next_boundary(self, time: OffsetDateTime) -> OffsetDateTime {
+        match self {
+            Self::Hourly => time.replace_time(Time::from_hms(time.hour(), 0, 0).unwrap_or(Time::MIDNIGHT)) + Duration::HOUR,
+            Self::Daily => time.replace_time(Time::MIDNIGHT) + Duration::DAY,
+        }
+    }
+}
+
+/// Settings of file used by `FileLogger` - when to rotate it and how to name archives
+#[derive(Debug, Clone)]
+pub struct FileConfig {
+    pub(crate) max_size: Option<u64>,
+    pub(crate) rotation_period: Option<RotationPeriod>,
+    pub(crate) archive_pattern: &'static str,
+    pub(crate) max_archives: Option<usize>,
+    pub(crate) max_archive_age: Option<std::time::Duration>,
+    pub(crate) max_total_size: Option<u64>,
+    pub(crate) compression: Compression,
+    pub(crate) reopen_on_sighup: bool,
+    pub(crate) reopen_on_file_change: bool,
+}
+
+/// Replaces tokens related to log file name in archive pattern
+/// Tokens that differ between archives, like time and index, are left untouched
+pub(crate) fn fill_file_name_tokens(path: &Path, pattern: &str) -> String {
+    let lossy = |text: Option<&std::ffi::OsStr>| text.map(|text| text.to_string_lossy().into_owned()).unwrap_or_default();
+    pattern
+        .replace("[_file_name]", &lossy(path.file_name()))
+        .replace("[_file_stem]", &lossy(path.file_stem()))
+        .replace("[_file_ext]", &lossy(path.extension()))
+}
+
+#[derive(Debug, Clone)]
+pub struct FileConfigBuilder(FileConfig);
+
+impl FileConfigBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(FileConfig::default())
+    }
+
+    /// Sets maximum size of log file in bytes
+    /// If next record would exceed this size, file is rotated before writing it
+    /// If size is none, file is never rotated because of its size
+    pub fn set_max_size(&mut self, max_size: Option<u64>) -> &mut Self {
+        self.0.max_size = max_size;
+        self
+    }
+
+    /// Sets period after which file is rotated, counted from full hour or midnight
+    /// in time offset used by logger config
+    /// If period is none, file is never rotated because of time
+    pub fn set_rotation_period(&mut self, rotation_period: Option<RotationPeriod>) -> &mut Self {
+        self.0.rotation_period = rotation_period;
+        self
+    }
+
+    /// Sets name of rotated files, which are placed in the same directory as log file
+    /// Available tokens:
+    /// \[_file_name\] - name of log file e.g. `app.log`
+    /// \[_file_stem\] - name of log file without extension e.g. `app`
+    /// \[_file_ext\] - extension of log file e.g. `log`
+    /// \[_time\] - time when archived file was started e.g. `2023-07-04_22-07-27`
+    /// \[_index\] - number starting from 1, increased when archive with such name already exists
+    /// Without \[_index\], conflicting archives get `.1`, `.2` etc. suffix
+    /// Default pattern is "\[_file_name\].\[_time\]"
+    pub fn set_archive_pattern(&mut self, archive_pattern: &'static str) -> &mut Self {
+        self.0.archive_pattern = archive_pattern;
+        self
+    }
+
+    /// Sets maximum number of kept archives, older ones are removed during rotation
+    /// If number is none, archives are not removed because of their number
+    pub fn set_max_archives(&mut self, max_archives: Option<usize>) -> &mut Self {
+        self.0.max_archives = max_archives;
+        self
+    }
+
+    /// Sets maximum age of archives, counted from their last modification
+    /// Older archives are removed during rotation
+    /// If age is none, archives are not removed because of their age
+    pub fn set_max_archive_age(&mut self, max_archive_age: Option<std::time::Duration>) -> &mut Self {
+        self.0.max_archive_age = max_archive_age;
+        self
+    }
+
+    /// Sets maximum size in bytes of log file together with all its archives
+    /// When it is exceeded, oldest archives are removed during rotation, current log file is always kept
+    /// If size is none, archives are not removed because of their total size
+    pub fn set_max_total_size(&mut self, max_total_size: Option<u64>) -> &mut Self {
+        self.0.max_total_size = max_total_size;
+        self
+    }
+
+    /// Sets compression of archives, which is done in background thread after rotation
+    /// Compressed archives get `.gz` or `.zst` suffix
+    /// Compressions are available only with `gzip` and `zstd` cargo features
+    pub fn set_compression(&mut self, compression: Compression) -> &mut Self {
+        self.0.compression = compression;
+        self
+    }
+
+    /// Reopens log file when process receives SIGHUP signal, which is usually sent by `logrotate`
+    /// in `postrotate` script
+    /// Installing handler replaces default SIGHUP action, which terminates process
+    /// Handlers installed before it, e.g. by `signal-hook` or `tokio`, are still called
+    /// Works only on unix, on other platforms use `FileLogger::reopen_handle`
+    pub fn set_reopen_on_sighup(&mut self, reopen_on_sighup: bool) -> &mut Self {
+        self.0.reopen_on_sighup = reopen_on_sighup;
+        self
+    }
+
+    /// Reopens log file when file at its path was removed or replaced by other file
+    /// Path is checked at most once per second, when logging record
+    /// Works only on unix
+    pub fn set_reopen_on_file_change(&mut self, reopen_on_file_change: bool) -> &mut Self {
+        self.0.reopen_on_file_change = reopen_on_file_change;
+        self
+    }
+
+    /// Builds the file config
+    pub fn build(&mut self) -> FileConfig {
+        self.0.clone()
+    }
+}
+
+impl Default for FileConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for FileConfig {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            rotation_period: None,
+            archive_pattern: "[_file_name].[_time]",
+            max_archives: None,
+            max_archive_age: None,
+            max_total_size: None,
+            compression: Compression::None,
+            reopen_on_sighup: false,
+            reopen_on_file_change: false,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::common::hostname;
+
+/// Settings of `GelfLogger`
+#[derive(Debug, Clone)]
+pub struct GelfConfig {
+    pub(crate) host: String,
+    pub(crate) chunk_size: usize,
+    #[cfg(feature = "gzip")]
+    pub(crate) compressed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GelfConfigBuilder(GelfConfig);
+
+impl GelfConfigBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(GelfConfig::default())
+    }
+
+    /// Sets `host` field, by default name of current machine
+    pub fn set_host(&mut self, host: &str) -> &mut Self {
+        self.0.host = host.to_string();
+        self
+    }
+
+    /// Sets maximum size of UDP datagram, bigger messages are split into chunks
+    /// By default 1420 bytes, which fits into most networks without fragmentation
+    pub fn set_chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.0.chunk_size = chunk_size.max(GELF_CHUNK_HEADER_SIZE + 1);
+        self
+    }
+
+    /// Compresses messages sent over UDP with gzip, TCP messages are never compressed
+    #[cfg(feature = "gzip")]
+    pub fn set_compressed(&mut self, compressed: bool) -> &mut Self {
+        self.0.compressed = compressed;
+        self
+    }
+
+    /// Builds the GELF config
+    pub fn build(&mut self) -> GelfConfig {
+        self.0.clone()
+    }
+}
+
+pub(crate) const GELF_CHUNK_HEADER_SIZE: usize = 12;
+
+impl Default for GelfConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for GelfConfig {
+    fn default() -> Self {
+        Self {
+            host: hostname(),
+            chunk_size: 1420,
+            #[cfg(feature = "gzip")]
+            compressed: false,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use log::{set_boxed_logger, set_max_level, Log, Metadata, Record, SetLoggerError};
+
+use crate::SharedLogger;
+
+/// Global logger, which shares inner logger with `LoggerGuard`
+pub(crate) struct GuardedLogger {
+    logger: Arc<dyn SharedLogger>,
+}
+
+impl Log for GuardedLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.logger.log(record);
+    }
+
+    fn flush(&self) {
+        self.logger.flush();
+    }
+}
+
+/// Guard which flushes global logger and stops its background threads when dropped
+/// The `log` crate never drops global logger, so without guard, records buffered by loggers
+/// or waiting in queue of `AsyncLogger` may be lost when application exits
+/// Guard should be kept until the end of `main`
+/// ```no_run
+/// use handsome_logger::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
+///
+/// let config = ConfigBuilder::new().set_flush_every_record(false).build();
+/// let _guard = TermLogger::init_with_guard(config, TerminalMode::Mixed, ColorChoice::Auto).unwrap();
+/// log::info!("Written to terminal at the latest when guard is dropped");
+/// ```
+#[must_use = "logger is shut down when guard is dropped"]
+pub struct LoggerGuard {
+    logger: Arc<dyn SharedLogger>,
+}
+
+impl LoggerGuard {
+    /// Sets logger as global logger and returns guard, which shuts it down when dropped
+    pub fn init(logger: Box<dyn SharedLogger>) -> Result<Self, SetLoggerError> {
+        let (guard, guarded) = Self::new(logger);
+        set_max_level(guard.logger.level());
+        set_boxed_logger(guarded)?;
+        Ok(guard)
+    }
+
+    /// Creates guard and logger, which is not set as global logger
+    pub(crate) fn new(logger: Box<dyn SharedLogger>) -> (Self, Box<GuardedLogger>) {
+        let logger: Arc<dyn SharedLogger> = Arc::from(logger);
+        (Self { logger: logger.clone() }, Box::new(GuardedLogger { logger }))
+    }
+}
+
+impl Drop for LoggerGuard {
+    fn drop(&mut self) {
+        self.logger.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, LevelFilter};
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::{AsyncLogger, CombinedLogger, OverflowPolicy, WriteLogger};
+
+    use super::*;
+
+    #[test]
+    fn test_drop_shuts_down_logger() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[_msg]", None).set_level(LevelFilter::Info).build();
+        let async_logger = AsyncLogger::new(WriteLogger::new(config, buffer.clone()), 100, OverflowPolicy::Block);
+        let (guard, logger) = LoggerGuard::new(CombinedLogger::new(vec![async_logger]));
+
+        for idx in 0..50 {
+            logger.log(&Record::builder().level(Level::Info).args(format_args!("Message {idx}")).build());
+        }
+        drop(guard);
+
+        let written = buffer.text();
+        assert_eq!(written.lines().count(), 50);
+        assert_eq!(written.lines().last(), Some("Message 49"));
+
+        // Records logged after shutdown are ignored
+        logger.log(&Record::builder().level(Level::Info).args(format_args!("Ignored")).build());
+        assert!(!buffer.text().contains("Ignored"));
+    }
+}
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+use crate::common::app_name;
+
+/// Settings of `JournaldLogger`
+#[derive(Debug, Clone)]
+pub struct JournaldConfig {
+    pub(crate) syslog_identifier: String,
+    pub(crate) socket_path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournaldConfigBuilder(JournaldConfig);
+
+impl JournaldConfigBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(JournaldConfig::default())
+    }
+
+    /// Sets `SYSLOG_IDENTIFIER` field, by default name of current executable
+    pub fn set_syslog_identifier(&mut self, syslog_identifier: &str) -> &mut Self {
+        self.0.syslog_identifier = syslog_identifier.to_string();
+        self
+    }
+
+    /// Sets path of journald socket, by default `/run/systemd/journal/socket`
+    pub fn set_socket_path<P: AsRef<Path>>(&mut self, socket_path: P) -> &mut Self {
+        self.0.socket_path = socket_path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Builds the journald config
+    pub fn build(&mut self) -> JournaldConfig {
+        self.0.clone()
+    }
+}
+
+impl Default for JournaldConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for JournaldConfig {
+    fn default() -> Self {
+        Self {
+            syslog_identifier: app_name(),
+            socket_path: PathBuf::from("/run/systemd/journal/socket"),
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use std::fmt::{self, Display};
+use std::io::{Error, Write};
+use std::sync::OnceLock;
+use std::{process, thread};
+
+use log::kv::Value;
+use log::{Level, Record};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::common::{app_name, hostname};
+use crate::config::{JsonField, JsonSchema};
+use crate::loggers::logging::write_thread_id;
+use crate::record::{for_each_key_value, record_time, with_replayed};
+use crate::Config;
+
+/// Writes record as one line JSON object, with fields of chosen schema
+pub(crate) fn write_json<W>(config: &Config, record: &Record, write: &mut W) -> Result<(), Error>
+where
+    W: Write + Sized,
+{
+    let mut object = JsonObject::new(write)?;
+    match config.json_schema {
+        JsonSchema::Default => write_default_fields(&mut object, config, record)?,
+        JsonSchema::Ecs => write_ecs_fields(&mut object, config, record)?,
+        JsonSchema::Gcp => write_gcp_fields(&mut object, config, record)?,
+        JsonSchema::Bunyan => write_bunyan_fields(&mut object, record)?,
+    }
+
+    if let Some(name) = config.json_key_values_field {
+        object.key(name)?;
+        let mut key_values = JsonObject::new(object.write)?;
+        write_key_values(&mut key_values, record, &[])?;
+        key_values.finish()?;
+    } else {
+        write_key_values(&mut object, record, schema_keys(config))?;
+    }
+
+    object.finish()?;
+    writeln!(write)?;
+    Ok(())
+}
+
+fn write_default_fields<W: Write>(object: &mut JsonObject<W>, config: &Config, record: &Record) -> Result<(), Error> {
+    if let Some(name) = config.json_field_names[JsonField::Timestamp as usize] {
+        object.timestamp(name, record_time().to_offset(config.time_offset))?;
+    }
+    if let Some(name) = config.json_field_names[JsonField::Level as usize] {
+        object.string(name, record.level())?;
+    }
+    if let Some(name) = config.json_field_names[JsonField::Target as usize] {
+        object.string(name, record.target())?;
+    }
+    if let (Some(name), Some(module)) = (config.json_field_names[JsonField::Module as usize], record.module_path()) {
+        object.string(name, module)?;
+    }
+    if let (Some(name), Some(file)) = (config.json_field_names[JsonField::File as usize], record.file()) {
+        object.string(name, file)?;
+    }
+    if let (Some(name), Some(line)) = (config.json_field_names[JsonField::Line as usize], record.line()) {
+        object.number(name, line)?;
+    }
+    if let Some(name) = config.json_field_names[JsonField::ThreadId as usize] {
+        object.key(name)?;
+        write_thread_id(object.write, config)?;
+    }
+    if let Some(name) = config.json_field_names[JsonField::ThreadName as usize] {
+        if let Some(thread_name) = thread_name() {
+            object.string(name, thread_name)?;
+        }
+    }
+    if let Some(name) = config.json_field_names[JsonField::Message as usize] {
+        object.string(name, record.args())?;
+    }
+    Ok(())
+}
+
+/// Elastic Common Schema - <https://www.elastic.co/guide/en/ecs-logging/overview/current/intro.html>
+fn write_ecs_fields<W: Write>(object: &mut JsonObject<W>, config: &Config, record: &Record) -> Result<(), Error> {
+    object.timestamp("@timestamp", record_time().to_offset(config.time_offset))?;
+    object.string("log.level", record.level().as_str().to_ascii_lowercase())?;
+    object.string("message", record.args())?;
+    object.string("ecs.version", "1.6.0")?;
+    object.string("log.logger", record.target())?;
+    if let Some(module) = record.module_path() {
+        object.string("log.origin.function", module)?;
+    }
+    if let Some(file) = record.file() {
+        object.string("log.origin.file.name", file)?;
+    }
+    if let Some(line) = record.line() {
+        object.number("log.origin.file.line", line)?;
+    }
+    object.key("process.thread.id")?;
+    write_thread_id(object.write, config)?;
+    if let Some(thread_name) = thread_name() {
+        object.string("process.thread.name", thread_name)?;
+    }
+    Ok(())
+}
+
+/// Google Cloud Logging structured logs - <https://cloud.google.com/logging/docs/structured-logging>
+fn write_gcp_fields<W: Write>(object: &mut JsonObject<W>, config: &Config, record: &Record) -> Result<(), Error> {
+    let severity = match record.level() {
+        Level::Error => "ERROR",
+        Level::Warn => "WARNING",
+        Level::Info => "INFO",
+        Level::Debug | Level::Trace => "DEBUG",
+    };
+    object.timestamp("timestamp", record_time().to_offset(config.time_offset))?;
+    object.string("severity", severity)?;
+    object.string("message", record.args())?;
+    object.string("target", record.target())?;
+    if record.file().is_some() || record.line().is_some() || record.module_path().is_some() {
+        object.key("logging.googleapis.com/sourceLocation")?;
+        let mut location = JsonObject::new(object.write)?;
+        if let Some(file) = record.file() {
+            location.string("file", file)?;
+        }
+        if let Some(line) = record.line() {
+            // Line is int64, which is encoded in JSON as string
+            location.string("line", line)?;
+        }
+        if let Some(module) = record.module_path() {
+            location.string("function", module)?;
+        }
+        location.finish()?;
+    }
+    Ok(())
+}
+
+/// Bunyan log record - <https://github.com/trentm/node-bunyan#core-fields>
+fn write_bunyan_fields<W: Write>(object: &mut JsonObject<W>, record: &Record) -> Result<(), Error> {
+    static PROCESS_INFO: OnceLock<(String, String)> = OnceLock::new();
+    let (name, hostname) = PROCESS_INFO.get_or_init(|| (app_name(), hostname()));
+
+    let level = match record.level() {
+        Level::Error => 50,
+        Level::Warn => 40,
+        Level::Info => 30,
+        Level::Debug => 20,
+        Level::Trace => 10,
+    };
+    object.number("v", 0)?;
+    object.number("level", level)?;
+    object.string("name", name)?;
+    object.string("hostname", hostname)?;
+    object.number("pid", process::id())?;
+    object.timestamp("time", record_time())?;
+    object.string("msg", record.args())?;
+    object.string("target", record.target())?;
+    if record.file().is_some() || record.line().is_some() {
+        object.key("src")?;
+        let mut src = JsonObject::new(object.write)?;
+        if let Some(file) = record.file() {
+            src.string("file", file)?;
+        }
+        if let Some(line) = record.line() {
+            src.number("line", line)?;
+        }
+        if let Some(module) = record.module_path() {
+            src.string("func", module)?;
+        }
+        src.finish()?;
+    }
+    Ok(())
+}
+
+/// Name of thread which logged record
+fn thread_name() -> Option<String> {
+    with_replayed(|replayed| match replayed {
+        Some(replayed) => replayed.thread_name().map(str::to_string),
+        None => thread::current().name().map(str::to_string),
+    })
+}
+
+/// Prefix added to key-value pairs written into main object, whose keys are already used by schema
+pub(crate) const COLLIDING_KEY_PREFIX: &str = "fields.";
+
+/// Top level keys, that may be written by schema
+fn schema_keys(config: &Config) -> &[Option<&'static str>] {
+    match config.json_schema {
+        JsonSchema::Default => &config.json_field_names,
+        JsonSchema::Ecs => &[
+            Some("@timestamp"),
+            Some("log.level"),
+            Some("message"),
+            Some("ecs.version"),
+            Some("log.logger"),
+            Some("log.origin.function"),
+            Some("log.origin.file.name"),
+            Some("log.origin.file.line"),
+            Some("process.thread.id"),
+            Some("process.thread.name"),
+        ],
+        JsonSchema::Gcp => &[
+            Some("timestamp"),
+            Some("severity"),
+            Some("message"),
+            Some("target"),
+            Some("logging.googleapis.com/sourceLocation"),
+        ],
+        JsonSchema::Bunyan => &[
+            Some("v"),
+            Some("level"),
+            Some("name"),
+            Some("hostname"),
+            Some("pid"),
+            Some("time"),
+            Some("msg"),
+            Some("target"),
+            Some("src"),
+        ],
+    }
+}
+
+/// Writes key-value pairs, keys from `reserved_keys` are prefixed, so object never contains duplicated keys
+fn write_key_values<W: Write>(object: &mut JsonObject<W>, record: &Record, reserved_keys: &[Option<&str>]) -> Result<(), Error> {
+    let mut result = Ok(());
+    for_each_key_value(record.key_values(), |key, value| {
+        if result.is_ok() {
+            let key = key.as_str();
+            result = if reserved_keys.contains(&Some(key)) {
+                object.value(&format!("{COLLIDING_KEY_PREFIX}{key}"), &value)
+            } else {
+                object.value(key, &value)
+            };
+        }
+    });
+    result
+}
+
+/// Helper which writes fields of JSON object, separating them with commas
+pub(crate) struct JsonObject<'a, W: Write> {
+    pub(crate) write: &'a mut W,
+    first: bool,
+}
+
+impl<'a, W: Write> JsonObject<'a, W> {
+    pub(crate) fn new(write: &'a mut W) -> Result<Self, Error> {
+        write.write_all(b"{")?;
+        Ok(Self { write, first: true })
+    }
+
+    pub(crate) fn key(&mut self, name: &str) -> Result<(), Error> {
+        if !self.first {
+            self.write.write_all(b",")?;
+        }
+        self.first = false;
+        write_string(self.write, name)?;
+        self.write.write_all(b":")
+    }
+
+    pub(crate) fn string<D: Display>(&mut self, name: &str, value: D) -> Result<(), Error> {
+        self.key(name)?;
+        write_string(self.write, value)
+    }
+
+    pub(crate) fn number<D: Display>(&mut self, name: &str, value: D) -> Result<(), Error> {
+        self.key(name)?;
+        write!(self.write, "{value}")
+    }
+
+    /// Writes time in RFC 3339 format
+    pub(crate) fn timestamp(&mut self, name: &str, time: OffsetDateTime) -> Result<(), Error> {
+        self.key(name)?;
+        self.write.write_all(b"\"")?;
+        time.format_into(self.write, &Rfc3339).map_err(|err| match err {
+            time::error::Format::StdIo(err) => err,
+            err => Error::other(err),
+        })?;
+        self.write.write_all(b"\"")
+    }
+
+    /// Writes numbers and booleans without quotes, other values as strings
+    pub(crate) fn value(&mut self, name: &str, value: &Value) -> Result<(), Error> {
+        self.key(name)?;
+        if let Some(value) = value.to_bool() {
+            write!(self.write, "{value}")
+        } else if let Some(value) = value.to_i64() {
+            write!(self.write, "{value}")
+        } else if let Some(value) = value.to_u64() {
+            write!(self.write, "{value}")
+        } else if let Some(value) = value.to_f64() {
+            if value.is_finite() {
+                write!(self.write, "{value}")
+            } else {
+                self.write.write_all(b"null")
+            }
+        } else {
+            write_string(self.write, value)
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<(), Error> {
+        self.write.write_all(b"}")
+    }
+}
+
+/// Writes displayed value as JSON string, escaping quotes, backslashes and control characters
+pub(crate) fn write_string<W, D>(write: &mut W, value: D) -> Result<(), Error>
+where
+    W: Write + ?Sized,
+    D: Display,
+{
+    write.write_all(b"\"")?;
+    let mut escaper = JsonEscaper { write, error: None };
+    if fmt::Write::write_fmt(&mut escaper, format_args!("{value}")).is_err() {
+        return Err(escaper.error.unwrap_or_else(|| Error::other("formatter error")));
+    }
+    write.write_all(b"\"")
+}
+
+struct JsonEscaper<'a, W: Write + ?Sized> {
+    write: &'a mut W,
+    error: Option<Error>,
+}
+
+impl<W: Write + ?Sized> JsonEscaper<'_, W> {
+    fn write_escaped(&mut self, text: &str) -> Result<(), Error> {
+        let mut start = 0;
+        for (idx, character) in text.char_indices() {
+            let escaped = match character {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                character if character < ' ' => "",
+                _ => continue,
+            };
+            self.write.write_all(&text.as_bytes()[start..idx])?;
+            if escaped.is_empty() {
+                write!(self.write, "\\u{:04x}", character as u32)?;
+            } else {
+                self.write.write_all(escaped.as_bytes())?;
+            }
+            start = idx + character.len_utf8();
+        }
+        self.write.write_all(&text.as_bytes()[start..])
+    }
+}
+
+impl<W: Write + ?Sized> fmt::Write for JsonEscaper<'_, W> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.write_escaped(text).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use crate::{ConfigBuilder, JsonSchema, OutputFormat};
+
+    use super::*;
+
+    fn to_json(config: &Config, record: &Record) -> String {
+        let mut buffer = Vec::new();
+        write_json(config, record, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_escaping() {
+        let mut buffer = Vec::new();
+        write_string(&mut buffer, "quote \" backslash \\ new line \n tab \t bell \u{7} zażółć").unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), r#""quote \" backslash \\ new line \n tab \t bell \u0007 zażółć""#);
+    }
+
+    /// Runs test on thread with known name, because name of test thread depends on test harness
+    fn on_worker_thread(test: fn()) {
+        thread::Builder::new().name("worker".to_string()).spawn(test).unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_json_record() {
+        on_worker_thread(json_record);
+    }
+
+    fn json_record() {
+        let config = ConfigBuilder::new()
+            .set_output_format(OutputFormat::Json)
+            .set_json_field_name(JsonField::Timestamp, None)
+            .set_json_field_name(JsonField::ThreadId, None)
+            .set_json_field_name(JsonField::ThreadName, None)
+            .set_json_field_name(JsonField::Message, Some("msg"))
+            .build();
+        let key_values = [
+            ("user", Value::from("jo\"hn")),
+            ("id", Value::from(42)),
+            ("ratio", Value::from(0.5)),
+            ("admin", Value::from(false)),
+        ];
+        let record = Record::builder()
+            .level(Level::Warn)
+            .target("server")
+            .module_path(Some("server::handler"))
+            .file(Some("src/handler.rs"))
+            .line(Some(7))
+            .key_values(&key_values)
+            .args(format_args!("Line\nother line"))
+            .build();
+        assert_eq!(
+            to_json(&config, &record),
+            "{\"level\":\"WARN\",\"target\":\"server\",\"module\":\"server::handler\",\"file\":\"src/handler.rs\",\"line\":7,\
+             \"msg\":\"Line\\nother line\",\"user\":\"jo\\\"hn\",\"id\":42,\"ratio\":0.5,\"admin\":false}\n"
+        );
+
+        let config = ConfigBuilder::new()
+            .set_output_format(OutputFormat::Json)
+            .set_json_field_name(JsonField::Timestamp, None)
+            .set_json_field_name(JsonField::Target, None)
+            .set_json_field_name(JsonField::ThreadId, None)
+            .set_json_key_values_field(Some("fields"))
+            .build();
+        assert_eq!(
+            to_json(&config, &record),
+            "{\"level\":\"WARN\",\"module\":\"server::handler\",\"file\":\"src/handler.rs\",\"line\":7,\"thread_name\":\"worker\",\
+             \"message\":\"Line\\nother line\",\"fields\":{\"user\":\"jo\\\"hn\",\"id\":42,\"ratio\":0.5,\"admin\":false}}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_colliding_keys() {
+        let key_values = [("level", Value::from("custom")), ("message", Value::from(1)), ("user", Value::from("john"))];
+        let record = Record::builder().level(Level::Info).target("server").key_values(&key_values).args(format_args!("Hello")).build();
+
+        let config = ConfigBuilder::new()
+            .set_output_format(OutputFormat::Json)
+            .set_json_field_name(JsonField::Timestamp, None)
+            .set_json_field_name(JsonField::ThreadId, None)
+            .set_json_field_name(JsonField::ThreadName, None)
+            .build();
+        assert_eq!(
+            to_json(&config, &record),
+            "{\"level\":\"INFO\",\"target\":\"server\",\"message\":\"Hello\",\"fields.level\":\"custom\",\"fields.message\":1,\"user\":\"john\"}\n"
+        );
+
+        let config = ConfigBuilder::new().set_output_format(OutputFormat::Json).set_json_schema(JsonSchema::Gcp).build();
+        let json = to_json(&config, &record);
+        assert!(json.ends_with("\"target\":\"server\",\"level\":\"custom\",\"fields.message\":1,\"user\":\"john\"}\n"), "{json}");
+    }
+
+    #[test]
+    fn test_json_timestamp() {
+        let config = ConfigBuilder::new().set_output_format(OutputFormat::Json).set_time_offset(time::UtcOffset::UTC).build();
+        let record = Record::builder().level(Level::Info).args(format_args!("Hello")).build();
+        let json = to_json(&config, &record);
+        let timestamp = json.strip_prefix("{\"timestamp\":\"").unwrap().split('"').next().unwrap();
+        assert_eq!(timestamp.as_bytes()[10], b'T', "{json}");
+        assert!(timestamp.ends_with('Z'), "{json}");
+        assert!(json.ends_with("\"message\":\"Hello\"}\n"), "{json}");
+    }
+
+    /// Removes string field with time, which is different in each run
+    fn without_time(json: &str, name: &str) -> String {
+        let prefix = format!("\"{name}\":\"");
+        let start = json.find(&prefix).unwrap();
+        let end = start + prefix.len() + json[start + prefix.len()..].find('"').unwrap() + 2;
+        format!("{}{}", &json[..start], &json[end..])
+    }
+
+    #[test]
+    fn test_json_schemas() {
+        on_worker_thread(json_schemas);
+    }
+
+    fn json_schemas() {
+        let key_values = [("user", Value::from("john"))];
+        let record = || {
+            Record::builder()
+                .level(Level::Warn)
+                .target("server")
+                .module_path_static(Some("server::handler"))
+                .file_static(Some("src/handler.rs"))
+                .line(Some(7))
+                .key_values(&key_values)
+                .args(format_args!("Hello"))
+                .build()
+        };
+        let schema_config = |json_schema| {
+            ConfigBuilder::new()
+                .set_output_format(OutputFormat::Json)
+                .set_json_schema(json_schema)
+                .set_thread_id_kind(crate::ThreadIdKind::Rust)
+                .build()
+        };
+
+        let thread_id = crate::loggers::logging::current_rust_thread_id();
+
+        let json = to_json(&schema_config(JsonSchema::Ecs), &record());
+        assert_eq!(
+            without_time(&json, "@timestamp"),
+            format!(
+                "{{\"log.level\":\"warn\",\"message\":\"Hello\",\"ecs.version\":\"1.6.0\",\"log.logger\":\"server\",\
+                 \"log.origin.function\":\"server::handler\",\"log.origin.file.name\":\"src/handler.rs\",\"log.origin.file.line\":7,\
+                 \"process.thread.id\":{thread_id},\"process.thread.name\":\"worker\",\"user\":\"john\"}}\n"
+            )
+        );
+
+        let json = to_json(&schema_config(JsonSchema::Gcp), &record());
+        assert_eq!(
+            without_time(&json, "timestamp"),
+            "{\"severity\":\"WARNING\",\"message\":\"Hello\",\"target\":\"server\",\
+             \"logging.googleapis.com/sourceLocation\":{\"file\":\"src/handler.rs\",\"line\":\"7\",\"function\":\"server::handler\"},\"user\":\"john\"}\n"
+        );
+
+        let json = to_json(&schema_config(JsonSchema::Bunyan), &record());
+        assert!(json.contains("Z\",\"msg\""), "{json}");
+        assert_eq!(
+            without_time(&json, "time"),
+            format!(
+                "{{\"v\":0,\"level\":40,\"name\":\"{}\",\"hostname\":\"{}\",\"pid\":{},\"msg\":\"Hello\",\"target\":\"server\",\
+                 \"src\":{{\"file\":\"src/handler.rs\",\"line\":7,\"func\":\"server::handler\"}},\"user\":\"john\"}}\n",
+                app_name(),
+                hostname(),
+                process::id()
+            )
+        );
+    }
+}
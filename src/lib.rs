@@ -2,12 +2,44 @@
 pub use log::{Level, LevelFilter, Log};
 pub use termcolor::{Color, ColorChoice};
 
-pub use self::config::{format_description, Config, ConfigBuilder, FormatItem, FormatText, TimeFormat};
-pub use self::loggers::{CombinedLogger, SimpleLogger, TermLogger, TerminalMode, WriteLogger};
+pub use self::compression::Compression;
+pub use self::config::{format_description, Config, ConfigBuilder, FormatItem, FormatText, JsonField, JsonSchema, OutputFormat, ThreadIdKind, TimeFormat};
+pub use self::error::InitError;
+pub use self::file_config::{FileConfig, FileConfigBuilder, RotationPeriod};
+pub use self::gelf_config::{GelfConfig, GelfConfigBuilder};
+pub use self::guard::LoggerGuard;
+#[cfg(target_os = "linux")]
+pub use self::journald_config::{JournaldConfig, JournaldConfigBuilder};
+#[cfg(target_os = "linux")]
+pub use self::loggers::JournaldLogger;
+pub use self::loggers::{AsyncLogger, CombinedHandle, CombinedLogger, DedupLogger, FileLogger, FlightRecorder, GelfLogger, GelfTransport, MemoryHandle, MemoryLimit, MemoryLogger, MemoryQuery, OverflowPolicy, RateLimitKey, RateLimitLogger, RecorderScope, SamplingLogger, SimpleLogger, SyslogLogger, SyslogTransport, TermLogger, TerminalMode, WriteLogger};
+pub use self::panic_hook::install_panic_hook;
+pub use self::record::OwnedRecord;
+pub use self::reload::LoggerHandle;
+pub use self::reopen::ReopenHandle;
+pub use self::syslog_config::{Facility, SyslogConfig, SyslogConfigBuilder, SyslogFormat};
 
 mod common;
+mod compression;
 mod config;
+mod env_filter;
+mod error;
+mod file_config;
+mod gelf_config;
+mod guard;
+#[cfg(target_os = "linux")]
+mod journald_config;
+mod json;
+mod logfmt;
 mod loggers;
+mod panic_hook;
+mod record;
+mod reload;
+mod reopen;
+mod retention;
+mod syslog_config;
+
+pub mod testing;
 
 pub fn init() -> Result<(), log::SetLoggerError> {
     TermLogger::init(Config::default(), TerminalMode::Mixed, ColorChoice::Auto)
@@ -21,5 +53,23 @@ pub fn init_without_local_time() -> Result<(), log::SetLoggerError> {
 pub trait SharedLogger: Log {
     fn level(&self) -> LevelFilter;
     fn config(&self) -> Option<&Config>;
+
+    /// Changes level of logger at runtime, returns false if logger doesn't support it
+    fn set_level(&mut self, _level: LevelFilter) -> bool {
+        false
+    }
+
+    /// Replaces config of logger at runtime, level is taken from config or its env variable
+    /// Returns false if logger doesn't support it
+    fn set_config(&mut self, _config: Config) -> bool {
+        false
+    }
+
+    /// Flushes logger and stops its background threads, records logged later may be ignored
+    /// Called by `LoggerGuard` when it is dropped
+    fn shutdown(&self) {
+        self.flush();
+    }
+
     fn as_log(self: Box<Self>) -> Box<dyn Log>;
 }
@@ -0,0 +1,203 @@
+use std::borrow::Cow;
+use std::io::{Error, Write};
+
+use log::kv::Value;
+use log::Record;
+use termcolor::WriteColor;
+use time::format_description::well_known::Rfc3339;
+
+use crate::json::{write_string, COLLIDING_KEY_PREFIX};
+use crate::record::{for_each_key_value, record_time};
+use crate::Config;
+
+/// Keys written for every record, key-values with same keys are prefixed to not duplicate them
+const RESERVED_KEYS: [&str; 5] = ["ts", "level", "target", "module", "msg"];
+
+/// Writes record as logfmt line e.g. `ts=2023-07-04T22:07:27.123Z level=info module=app msg="Hello world" user=john`
+/// If `colored_keys` is true, keys are colored with color of record level
+pub(crate) fn write_logfmt<W>(config: &Config, record: &Record, write: &mut W, colored_keys: bool) -> Result<(), Error>
+where
+    W: WriteColor,
+{
+    let mut line = LogfmtLine {
+        write,
+        config,
+        record,
+        colored_keys,
+        first: true,
+    };
+
+    line.key("ts")?;
+    let time = record_time().to_offset(config.time_offset);
+    time.format_into(line.write, &Rfc3339).map_err(|err| match err {
+        time::error::Format::StdIo(err) => err,
+        err => Error::other(err),
+    })?;
+
+    line.key("level")?;
+    write!(line.write, "{}", record.level().as_str().to_ascii_lowercase())?;
+    line.pair("target", record.target())?;
+    if let Some(module) = record.module_path() {
+        line.pair("module", module)?;
+    }
+    match record.args().as_str() {
+        Some(message) => line.pair("msg", message)?,
+        None => line.pair("msg", &record.args().to_string())?,
+    }
+
+    let mut result = Ok(());
+    for_each_key_value(record.key_values(), |key, value| {
+        if result.is_ok() {
+            let key = key.as_str();
+            result = if RESERVED_KEYS.contains(&key) {
+                line.value(&format!("{COLLIDING_KEY_PREFIX}{key}"), &value)
+            } else {
+                line.value(key, &value)
+            };
+        }
+    });
+    result?;
+
+    writeln!(write)
+}
+
+struct LogfmtLine<'a, W: WriteColor> {
+    write: &'a mut W,
+    config: &'a Config,
+    record: &'a Record<'a>,
+    colored_keys: bool,
+    first: bool,
+}
+
+impl<W: WriteColor> LogfmtLine<'_, W> {
+    fn key(&mut self, key: &str) -> Result<(), Error> {
+        if !self.first {
+            self.write.write_all(b" ")?;
+        }
+        self.first = false;
+
+        if self.colored_keys {
+            self.write.set_color(&self.config.compiled_colors[self.record.level() as usize])?;
+        }
+        self.write.write_all(logfmt_key(key).as_bytes())?;
+        if self.colored_keys {
+            self.write.reset()?;
+        }
+        self.write.write_all(b"=")
+    }
+
+    fn pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        self.key(key)?;
+        write_value(self.write, value)
+    }
+
+    /// Writes numbers and booleans directly, other values quoted if needed
+    fn value(&mut self, key: &str, value: &Value) -> Result<(), Error> {
+        self.key(key)?;
+        if let Some(value) = value.to_bool() {
+            write!(self.write, "{value}")
+        } else if let Some(value) = value.to_i64() {
+            write!(self.write, "{value}")
+        } else if let Some(value) = value.to_u64() {
+            write!(self.write, "{value}")
+        } else if let Some(value) = value.to_f64() {
+            write!(self.write, "{value}")
+        } else {
+            write_value(self.write, &value.to_string())
+        }
+    }
+}
+
+/// Replaces characters, which are not allowed in logfmt keys, with underscores
+fn logfmt_key(key: &str) -> Cow<'_, str> {
+    if !key.is_empty() && key.chars().all(|character| character > ' ' && character != '=' && character != '"') {
+        Cow::Borrowed(key)
+    } else if key.is_empty() {
+        Cow::Borrowed("_")
+    } else {
+        Cow::Owned(key.chars().map(|character| if character > ' ' && character != '=' && character != '"' { character } else { '_' }).collect())
+    }
+}
+
+/// Writes value, quoting and escaping it when it is empty or contains spaces, quotes, equal signs or control characters
+fn write_value<W: Write + ?Sized>(write: &mut W, value: &str) -> Result<(), Error> {
+    if needs_quoting(value) {
+        write_string(write, value)
+    } else {
+        write.write_all(value.as_bytes())
+    }
+}
+
+fn needs_quoting(value: &str) -> bool {
+    value.is_empty() || value.chars().any(|character| character <= ' ' || character == '"' || character == '=' || character == '\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+    use termcolor::{Buffer, NoColor};
+
+    use crate::ConfigBuilder;
+
+    use super::*;
+
+    fn to_logfmt(config: &Config, record: &Record) -> String {
+        let mut buffer = NoColor::new(Vec::new());
+        write_logfmt(config, record, &mut buffer, false).unwrap();
+        String::from_utf8(buffer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_logfmt_record() {
+        let config = ConfigBuilder::new().set_time_offset(time::UtcOffset::UTC).build();
+        let key_values = [
+            ("user", Value::from("john")),
+            ("full name", Value::from("John \"Johnny\" Smith")),
+            ("id", Value::from(42)),
+            ("empty", Value::from("")),
+            ("path", Value::from("a=b")),
+        ];
+        let line = to_logfmt(
+            &config,
+            &Record::builder()
+                .level(Level::Warn)
+                .target("server")
+                .module_path(Some("server::handler"))
+                .key_values(&key_values)
+                .args(format_args!("Request {}\nfailed", 5))
+                .build(),
+        );
+
+        let rest = line.strip_prefix("ts=").unwrap();
+        let (timestamp, rest) = rest.split_once(' ').unwrap();
+        assert!(timestamp.ends_with('Z'), "{line}");
+        assert_eq!(
+            rest,
+            "level=warn target=server module=server::handler msg=\"Request 5\\nfailed\" user=john full_name=\"John \\\"Johnny\\\" Smith\" \
+             id=42 empty=\"\" path=\"a=b\"\n"
+        );
+    }
+
+    #[test]
+    fn test_colliding_keys() {
+        let config = ConfigBuilder::new().build();
+        let key_values = [("level", Value::from("high")), ("msg", Value::from("other")), ("user", Value::from("john"))];
+        let line = to_logfmt(&config, &Record::builder().level(Level::Info).target("app").key_values(&key_values).args(format_args!("Hello")).build());
+
+        let (_, rest) = line.split_once(' ').unwrap();
+        assert_eq!(rest, "level=info target=app msg=Hello fields.level=high fields.msg=other user=john\n");
+    }
+
+    #[test]
+    fn test_colored_keys() {
+        let mut config = ConfigBuilder::new().set_enabled_colours(true).build();
+        config.calculate_data();
+        let record = Record::builder().level(Level::Error).args(format_args!("Hello")).build();
+
+        let mut buffer = Buffer::ansi();
+        write_logfmt(&config, &record, &mut buffer, true).unwrap();
+        let line = String::from_utf8(buffer.into_inner()).unwrap();
+        assert!(line.contains("\u{1b}[0m=error"), "{line}");
+        assert!(line.contains("msg\u{1b}[0m=Hello"), "{line}");
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use log::{set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::record::OwnedRecord;
+use crate::{Config, LoggerGuard, SharedLogger};
+
+/// What to do with new record, when queue of `AsyncLogger` is full
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub enum OverflowPolicy {
+    /// Waits until writer thread makes space in queue, so no record is lost
+    #[default]
+    Block,
+    /// Ignores new record
+    DropNewest,
+    /// Removes oldest record from queue to make space for new one
+    DropOldest,
+}
+
+enum Message {
+    Record(OwnedRecord),
+    Flush(SyncSender<()>),
+}
+
+struct Queue {
+    messages: VecDeque<Message>,
+    shutdown: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
+
+/// Logger that formats and writes records of inner logger in dedicated background thread
+/// Logging thread only copies record and puts it into bounded queue, so slow targets like
+/// files on network disks don't block it
+pub struct AsyncLogger {
+    logger: Arc<dyn SharedLogger>,
+    shared: Arc<Shared>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl AsyncLogger {
+    pub fn init(logger: Box<dyn SharedLogger>, capacity: usize, policy: OverflowPolicy) -> Result<(), SetLoggerError> {
+        let logger = Self::new(logger, capacity, policy);
+        set_max_level(logger.level());
+        set_boxed_logger(logger)
+    }
+
+    /// Sets logger as global logger and returns guard, which writes queued records and stops
+    /// writer thread when dropped
+    pub fn init_with_guard(logger: Box<dyn SharedLogger>, capacity: usize, policy: OverflowPolicy) -> Result<LoggerGuard, SetLoggerError> {
+        LoggerGuard::init(Self::new(logger, capacity, policy))
+    }
+
+    /// Creates logger which sends records to `logger` through queue that holds at most `capacity` records
+    /// # Panics
+    /// Panics if writer thread cannot be spawned
+    #[must_use]
+    pub fn new(logger: Box<dyn SharedLogger>, capacity: usize, policy: OverflowPolicy) -> Box<Self> {
+        let logger: Arc<dyn SharedLogger> = Arc::from(logger);
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                messages: VecDeque::with_capacity(capacity),
+                shutdown: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity: capacity.max(1),
+            policy,
+            dropped: AtomicU64::new(0),
+        });
+
+        let worker_logger = logger.clone();
+        let worker_shared = shared.clone();
+        let worker = thread::Builder::new()
+            .name("handsome_logger_async".to_string())
+            .spawn(move || write_records(&*worker_logger, &worker_shared))
+            .expect("Failed to spawn async logger thread");
+
+        Box::new(Self {
+            logger,
+            shared,
+            worker: Mutex::new(Some(worker)),
+        })
+    }
+
+    /// Number of records dropped so far, which were not yet reported into log
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Writes all queued records and stops writer thread
+    /// Records logged after this call are ignored
+    pub fn shutdown(&self) {
+        self.shared.queue.lock().unwrap().shutdown = true;
+        self.shared.not_empty.notify_all();
+        self.shared.not_full.notify_all();
+
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+
+    fn push(&self, message: Message) {
+        let shared = &self.shared;
+        let mut queue = shared.queue.lock().unwrap();
+
+        if let Message::Record(_) = message {
+            while queue.messages.len() >= shared.capacity && !queue.shutdown {
+                match shared.policy {
+                    OverflowPolicy::Block => queue = shared.not_full.wait(queue).unwrap(),
+                    OverflowPolicy::DropNewest => {
+                        shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    OverflowPolicy::DropOldest => {
+                        // Flush requests are never dropped, because some thread waits for them
+                        match queue.messages.iter().position(|message| matches!(message, Message::Record(_))) {
+                            Some(idx) => {
+                                queue.messages.remove(idx);
+                                shared.dropped.fetch_add(1, Ordering::Relaxed);
+                            }
+                            None => break,
+                        }
+                    }
+                }
+            }
+        }
+
+        if queue.shutdown {
+            return;
+        }
+        queue.messages.push_back(message);
+        drop(queue);
+        shared.not_empty.notify_one();
+    }
+}
+
+/// Main loop of writer thread, takes all available messages at once to keep lock time short
+fn write_records(logger: &dyn SharedLogger, shared: &Shared) {
+    let mut batch = Vec::new();
+    loop {
+        let shutdown = {
+            let mut queue = shared.queue.lock().unwrap();
+            while queue.messages.is_empty() && !queue.shutdown {
+                queue = shared.not_empty.wait(queue).unwrap();
+            }
+            batch.extend(queue.messages.drain(..));
+            queue.shutdown
+        };
+        shared.not_full.notify_all();
+
+        report_dropped(logger, shared);
+        for message in batch.drain(..) {
+            match message {
+                Message::Record(record) => record.replay(|record| logger.log(record)),
+                Message::Flush(done) => {
+                    logger.flush();
+                    let _ = done.send(());
+                }
+            }
+        }
+
+        if shutdown {
+            report_dropped(logger, shared);
+            logger.flush();
+            return;
+        }
+    }
+}
+
+fn report_dropped(logger: &dyn SharedLogger, shared: &Shared) {
+    let dropped = shared.dropped.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("handsome_logger")
+                .module_path_static(Some(module_path!()))
+                .args(format_args!("AsyncLogger dropped {dropped} records, because its queue was full"))
+                .build(),
+        );
+    }
+}
+
+impl Log for AsyncLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.push(Message::Record(OwnedRecord::from_record(record)));
+        }
+    }
+
+    /// Waits until all records queued before this call are written and flushed
+    fn flush(&self) {
+        let (done_sender, done_receiver) = sync_channel(1);
+        self.push(Message::Flush(done_sender));
+        // Error means that writer thread is already stopped
+        let _ = done_receiver.recv();
+    }
+}
+
+impl SharedLogger for AsyncLogger {
+    fn level(&self) -> LevelFilter {
+        self.logger.level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        self.logger.config()
+    }
+
+    /// Writes all queued records, stops writer thread and shuts down inner logger
+    fn shutdown(&self) {
+        AsyncLogger::shutdown(self);
+        self.logger.shutdown();
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+impl Drop for AsyncLogger {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::WriteLogger;
+
+    use super::*;
+
+    /// Write target which is blocked until test allows it to continue
+    struct SlowWriter {
+        buffer: SharedBuffer,
+        gate: Arc<Mutex<()>>,
+    }
+
+    impl Write for SlowWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let _gate = self.gate.lock().unwrap();
+            self.buffer.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn log_message(logger: &AsyncLogger, message: &str) {
+        logger.log(&Record::builder().level(Level::Info).args(format_args!("{message}")).build());
+    }
+
+    #[test]
+    fn test_records_are_written_in_order_by_other_thread() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[_msg] [_thread_name]", None).set_level(LevelFilter::Trace).build();
+        let logger = AsyncLogger::new(WriteLogger::new(config, buffer.clone()), 16, OverflowPolicy::Block);
+
+        thread::scope(|scope| {
+            thread::Builder::new()
+                .name("producer".to_string())
+                .spawn_scoped(scope, || {
+                    for idx in 0..100 {
+                        log_message(&logger, &idx.to_string());
+                    }
+                })
+                .unwrap();
+        });
+        logger.flush();
+
+        let expected: Vec<String> = (0..100).map(|idx| format!("{idx} producer")).collect();
+        assert_eq!(buffer.lines(), expected);
+    }
+
+    fn check_overflow(policy: OverflowPolicy, expected: &[&str]) {
+        let buffer = SharedBuffer::default();
+        let gate = Arc::new(Mutex::new(()));
+        let config = test_config_builder().set_format_text("[_msg]", None).build();
+        let writer = SlowWriter {
+            buffer: buffer.clone(),
+            gate: gate.clone(),
+        };
+        let logger = AsyncLogger::new(WriteLogger::new(config, writer), 2, policy);
+
+        let gate_lock = gate.lock().unwrap();
+        log_message(&logger, "first");
+        // Wait until writer thread takes first record and is blocked on writing it
+        while !logger.shared.queue.lock().unwrap().messages.is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        for message in ["a", "b", "c", "d"] {
+            log_message(&logger, message);
+        }
+        assert_eq!(logger.dropped(), 2);
+        drop(gate_lock);
+        logger.flush();
+
+        assert_eq!(buffer.lines(), expected);
+        assert_eq!(logger.dropped(), 0);
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        check_overflow(OverflowPolicy::DropNewest, &["first", "AsyncLogger dropped 2 records, because its queue was full", "a", "b"]);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        check_overflow(OverflowPolicy::DropOldest, &["first", "AsyncLogger dropped 2 records, because its queue was full", "c", "d"]);
+    }
+
+    #[test]
+    fn test_shutdown_writes_pending_records() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[_msg]", None).build();
+        let logger = AsyncLogger::new(WriteLogger::new(config, buffer.clone()), 100, OverflowPolicy::Block);
+        for idx in 0..10 {
+            log_message(&logger, &idx.to_string());
+        }
+        drop(logger);
+
+        assert_eq!(buffer.lines().len(), 10);
+    }
+}
@@ -1,45 +1,173 @@
-use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use crate::common::get_env_log;
-use crate::{Config, SharedLogger};
+use arc_swap::ArcSwap;
+use log::{max_level, set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 
+use crate::{Config, LoggerGuard, LoggerHandle, SharedLogger};
+
+/// Maximum number of targets with cached level, levels of other targets are computed on each call
+const MAX_CACHED_TARGETS: usize = 1024;
+
+/// Most verbose level accepted by any of loggers for each already seen target
+/// Cache is replaced as a whole when target is added, so it is read without locking
+#[derive(Default)]
+struct TargetLevels(ArcSwap<HashMap<String, LevelFilter>>);
+
+impl TargetLevels {
+    fn get(&self, target: &str, enabled: impl Fn(&Metadata) -> bool) -> LevelFilter {
+        if let Some(level) = self.0.load().get(target) {
+            return *level;
+        }
+
+        let level = Level::iter()
+            .filter(|level| enabled(&Metadata::builder().target(target).level(*level).build()))
+            .map(|level| level.to_level_filter())
+            .max()
+            .unwrap_or(LevelFilter::Off);
+        if self.0.load().len() < MAX_CACHED_TARGETS {
+            self.0.rcu(|levels| {
+                let mut levels = HashMap::clone(levels);
+                if levels.len() < MAX_CACHED_TARGETS {
+                    levels.insert(target.to_string(), level);
+                }
+                levels
+            });
+        }
+        level
+    }
+
+    fn clear(&mut self) {
+        self.0.store(Arc::default());
+    }
+}
+
+/// Named loggers added by `CombinedHandle`, replaced as a whole on each change
+struct DynamicLoggers {
+    loggers: Vec<(String, Arc<dyn SharedLogger>)>,
+    level: LevelFilter,
+    target_levels: TargetLevels,
+}
+
+impl DynamicLoggers {
+    fn new(loggers: Vec<(String, Arc<dyn SharedLogger>)>) -> Self {
+        let level = loggers.iter().map(|(_, log)| log.level()).max().unwrap_or(LevelFilter::Off);
+        Self {
+            loggers,
+            level,
+            target_levels: TargetLevels::default(),
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+            && metadata.level() <= self.target_levels.get(metadata.target(), |metadata| self.loggers.iter().any(|(_, log)| log.enabled(metadata)))
+    }
+}
+
+struct Shared {
+    dynamic: ArcSwap<DynamicLoggers>,
+    global: AtomicBool,
+    /// Level of loggers given at creation, used to lower `log::max_level` after removing logger
+    fixed_level: AtomicUsize,
+}
+
+impl Shared {
+    fn max_level(&self) -> LevelFilter {
+        let fixed_level = LevelFilter::iter().nth(self.fixed_level.load(Ordering::Relaxed)).unwrap_or(LevelFilter::Trace);
+        fixed_level.max(self.dynamic.load().level)
+    }
+}
+
+/// Logger, which passes records to all inner loggers
+/// Record is enabled if any inner logger accepts it, including target directives of inner loggers
 pub struct CombinedLogger {
     level: LevelFilter,
     logger: Vec<Box<dyn SharedLogger>>,
+    target_levels: TargetLevels,
+    shared: Arc<Shared>,
 }
 
 impl CombinedLogger {
     pub fn init(logger: Vec<Box<dyn SharedLogger>>) -> Result<(), SetLoggerError> {
         let comblog = Self::new(logger);
-        let log_level = get_env_log().unwrap_or(comblog.level());
-        set_max_level(log_level);
+        comblog.shared.global.store(true, Ordering::Relaxed);
+        set_max_level(comblog.level());
         set_boxed_logger(comblog)
     }
 
+    /// Level of combined logger is the most verbose level of inner loggers, which already include
+    /// overrides from their environment variables, so it can be used as `log::max_level`
     #[must_use]
     pub fn new(logger: Vec<Box<dyn SharedLogger>>) -> Box<Self> {
-        let mut log_level = LevelFilter::Off;
-        for log in &logger {
-            if log_level < log.level() {
-                log_level = log.level();
-            }
-        }
-        let log_level = get_env_log().unwrap_or(log_level);
+        let log_level = fixed_max_level(&logger);
+
+        Box::new(Self {
+            level: log_level,
+            logger,
+            target_levels: TargetLevels::default(),
+            shared: Arc::new(Shared {
+                dynamic: ArcSwap::from_pointee(DynamicLoggers::new(Vec::new())),
+                global: AtomicBool::new(false),
+                fixed_level: AtomicUsize::new(log_level as usize),
+            }),
+        })
+    }
+
+    /// Sets logger as global logger and returns handle, which allows to change its level and config
+    pub fn init_with_handle(logger: Vec<Box<dyn SharedLogger>>) -> Result<LoggerHandle, SetLoggerError> {
+        let comblog = Self::new(logger);
+        comblog.shared.global.store(true, Ordering::Relaxed);
+        LoggerHandle::init(comblog)
+    }
+
+    /// Sets logger as global logger and returns guard, which flushes and shuts down inner loggers
+    /// when dropped
+    pub fn init_with_guard(logger: Vec<Box<dyn SharedLogger>>) -> Result<LoggerGuard, SetLoggerError> {
+        let comblog = Self::new(logger);
+        comblog.shared.global.store(true, Ordering::Relaxed);
+        LoggerGuard::init(comblog)
+    }
+
+    /// Returns handle, which allows to add and remove named loggers at runtime, should be taken
+    /// before logger is moved
+    pub fn handle(&self) -> CombinedHandle {
+        CombinedHandle { shared: self.shared.clone() }
+    }
 
-        Box::new(Self { level: log_level, logger })
+    fn enabled_with(&self, metadata: &Metadata, dynamic: &DynamicLoggers) -> bool {
+        let fixed_enabled = metadata.level() <= self.level
+            && metadata.level() <= self.target_levels.get(metadata.target(), |metadata| self.logger.iter().any(|log| log.enabled(metadata)));
+        fixed_enabled || dynamic.enabled(metadata)
     }
+
+    /// Recalculates level after inner loggers were changed
+    fn refresh_levels(&mut self) {
+        self.level = fixed_max_level(&self.logger);
+        self.shared.fixed_level.store(self.level as usize, Ordering::Relaxed);
+        self.target_levels.clear();
+    }
+}
+
+fn fixed_max_level(logger: &[Box<dyn SharedLogger>]) -> LevelFilter {
+    logger.iter().map(|log| log.level()).max().unwrap_or(LevelFilter::Off)
 }
 
 impl Log for CombinedLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        self.enabled_with(metadata, &self.shared.dynamic.load())
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
+        let dynamic = self.shared.dynamic.load();
+        if self.enabled_with(record.metadata(), &dynamic) {
             for log in &self.logger {
                 log.log(record);
             }
+            for (_, log) in &dynamic.loggers {
+                log.log(record);
+            }
         }
     }
 
@@ -47,19 +175,216 @@ impl Log for CombinedLogger {
         for log in &self.logger {
             log.flush();
         }
+        for (_, log) in &self.shared.dynamic.load().loggers {
+            log.flush();
+        }
+    }
+}
+
+/// Handle which adds and removes named loggers of `CombinedLogger` at runtime, from any thread
+/// Logging threads read list of loggers without locking
+/// Added loggers are not affected by `set_level` and `set_config` of combined logger, to change
+/// them, logger should be inserted again with the same name
+#[derive(Clone)]
+pub struct CombinedHandle {
+    shared: Arc<Shared>,
+}
+
+impl CombinedHandle {
+    /// Adds logger with given name, replacing and flushing previous logger with the same name
+    /// Returns true if logger was replaced
+    /// If combined logger is global logger, `log::max_level` is raised when needed
+    pub fn insert(&self, name: &str, logger: Box<dyn SharedLogger>) -> bool {
+        let logger: Arc<dyn SharedLogger> = Arc::from(logger);
+        let previous = self.update(|loggers| {
+            let mut loggers: Vec<_> = loggers.iter().filter(|(logger_name, _)| logger_name != name).cloned().collect();
+            loggers.push((name.to_string(), logger.clone()));
+            loggers
+        });
+        if self.shared.global.load(Ordering::Relaxed) && logger.level() > max_level() {
+            set_max_level(logger.level());
+        }
+        Self::flush_removed(&previous, name)
+    }
+
+    /// Removes and flushes logger with given name, returns false if there was no such logger
+    /// If combined logger is global logger, `log::max_level` is lowered when possible
+    pub fn remove(&self, name: &str) -> bool {
+        let previous = self.update(|loggers| loggers.iter().filter(|(logger_name, _)| logger_name != name).cloned().collect());
+        if self.shared.global.load(Ordering::Relaxed) {
+            set_max_level(self.shared.max_level());
+        }
+        Self::flush_removed(&previous, name)
+    }
+
+    /// Names of added loggers, in order of adding
+    pub fn names(&self) -> Vec<String> {
+        self.shared.dynamic.load().loggers.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn update<F>(&self, f: F) -> Arc<DynamicLoggers>
+    where
+        F: Fn(&[(String, Arc<dyn SharedLogger>)]) -> Vec<(String, Arc<dyn SharedLogger>)>,
+    {
+        self.shared.dynamic.rcu(|current| DynamicLoggers::new(f(&current.loggers)))
+    }
+
+    fn flush_removed(previous: &DynamicLoggers, name: &str) -> bool {
+        match previous.loggers.iter().find(|(logger_name, _)| logger_name == name) {
+            Some((_, logger)) => {
+                logger.flush();
+                true
+            }
+            None => false,
+        }
     }
 }
 
 impl SharedLogger for CombinedLogger {
     fn level(&self) -> LevelFilter {
-        self.level
+        self.level.max(self.shared.dynamic.load().level)
     }
 
     fn config(&self) -> Option<&Config> {
         None
     }
 
+    /// Sets level of all inner loggers, except added by `CombinedHandle`
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        let mut changed = true;
+        for log in &mut self.logger {
+            changed &= log.set_level(level);
+        }
+        self.refresh_levels();
+        changed
+    }
+
+    /// Sets config of all inner loggers, except added by `CombinedHandle`, level is recalculated
+    /// from their new levels
+    fn set_config(&mut self, config: Config) -> bool {
+        let mut changed = true;
+        for log in &mut self.logger {
+            changed &= log.set_config(config.clone());
+        }
+        self.refresh_levels();
+        changed
+    }
+
+    /// Shuts down all inner loggers, including added by `CombinedHandle`
+    fn shutdown(&self) {
+        for log in &self.logger {
+            log.shutdown();
+        }
+        for (_, log) in &self.shared.dynamic.load().loggers {
+            log.shutdown();
+        }
+    }
+
     fn as_log(self: Box<Self>) -> Box<dyn Log> {
         Box::new(*self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::sink;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::env_filter::EnvFilter;
+    use crate::{ConfigBuilder, WriteLogger};
+
+    use super::*;
+
+    /// Logger writing messages of records accepted by directives, like set in `RUST_LOG`
+    fn target_logger(buffer: &SharedBuffer, directives: &str, level: LevelFilter) -> Box<WriteLogger<SharedBuffer>> {
+        let mut config = test_config_builder().set_format_text("[_msg]", None).set_level(level).build();
+        config.env_filter = EnvFilter::parse(directives).map(Arc::new);
+        WriteLogger::new(config, buffer.clone())
+    }
+
+    fn enabled(logger: &CombinedLogger, target: &str, level: Level) -> bool {
+        logger.enabled(&Metadata::builder().target(target).level(level).build())
+    }
+
+    fn log_message(logger: &CombinedLogger, target: &str, level: Level, message: &str) {
+        logger.log(&Record::builder().target(target).level(level).args(format_args!("{message}")).build());
+    }
+
+    #[test]
+    fn test_enabled_by_children() {
+        let buffer = SharedBuffer::default();
+        let network = target_logger(&buffer, "app::network", LevelFilter::Trace);
+        let database = target_logger(&buffer, "app::db", LevelFilter::Warn);
+        let mut logger = CombinedLogger::new(vec![network, database]);
+        assert_eq!(logger.level(), LevelFilter::Trace);
+
+        assert!(enabled(&logger, "app::network::http", Level::Trace));
+        assert!(enabled(&logger, "app::db", Level::Warn));
+        assert!(!enabled(&logger, "app::db", Level::Info));
+        assert!(!enabled(&logger, "other", Level::Error));
+
+        log_message(&logger, "app::db", Level::Error, "Database");
+        log_message(&logger, "other", Level::Error, "Other");
+        assert_eq!(buffer.lines(), ["Database"]);
+
+        // Cached levels are recalculated after change of inner loggers
+        assert!(logger.set_level(LevelFilter::Info));
+        assert_eq!(logger.level(), LevelFilter::Info);
+        assert_eq!(logger.shared.max_level(), LevelFilter::Info);
+        assert!(!enabled(&logger, "app::network", Level::Debug));
+        assert!(enabled(&logger, "app::db", Level::Info));
+    }
+
+    #[test]
+    fn test_cached_targets_are_limited() {
+        let buffer = SharedBuffer::default();
+        let logger = CombinedLogger::new(vec![target_logger(&buffer, "app", LevelFilter::Info)]);
+        for idx in 0..MAX_CACHED_TARGETS * 2 {
+            assert!(enabled(&logger, &format!("app::module{idx}"), Level::Info));
+            assert!(!enabled(&logger, &format!("other::module{idx}"), Level::Info));
+        }
+        assert_eq!(logger.target_levels.0.load().len(), MAX_CACHED_TARGETS);
+    }
+
+    #[test]
+    fn test_per_logger_env_var() {
+        let file_config = ConfigBuilder::new().set_env_var(None).set_level(LevelFilter::Trace).build();
+        let term_config = ConfigBuilder::new().set_env_var(Some("HANDSOME_LOGGER_NOT_SET")).set_level(LevelFilter::Warn).build();
+        let file_logger = WriteLogger::new(file_config, sink());
+        let term_logger = WriteLogger::new(term_config, sink());
+        assert_eq!(file_logger.level(), LevelFilter::Trace);
+        assert_eq!(term_logger.level(), LevelFilter::Warn);
+
+        let logger = CombinedLogger::new(vec![file_logger, term_logger]);
+        assert_eq!(logger.level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn test_add_and_remove_loggers() {
+        let fixed_buffer = SharedBuffer::default();
+        let session_buffer = SharedBuffer::default();
+        let logger = CombinedLogger::new(vec![target_logger(&fixed_buffer, "app", LevelFilter::Warn)]);
+        let handle = logger.handle();
+
+        log_message(&logger, "app::network", Level::Debug, "Hidden");
+        assert!(!enabled(&logger, "app::network", Level::Debug));
+
+        assert!(!handle.insert("session", target_logger(&session_buffer, "app::network", LevelFilter::Trace)));
+        assert_eq!(handle.names(), ["session"]);
+        assert_eq!(logger.level(), LevelFilter::Trace);
+        assert!(enabled(&logger, "app::network", Level::Debug));
+        assert!(!enabled(&logger, "app::db", Level::Debug));
+        log_message(&logger, "app::network", Level::Debug, "Session");
+        assert_eq!(session_buffer.lines(), ["Session"]);
+        assert!(fixed_buffer.lines().is_empty());
+
+        assert!(handle.remove("session"));
+        assert!(!handle.remove("session"));
+        assert!(handle.names().is_empty());
+        assert_eq!(logger.level(), LevelFilter::Warn);
+        // Level used to lower `log::max_level` by handle
+        assert_eq!(handle.shared.max_level(), LevelFilter::Warn);
+        log_message(&logger, "app::network", Level::Debug, "Hidden");
+        assert_eq!(session_buffer.lines(), ["Session"]);
+    }
+}
@@ -0,0 +1,310 @@
+use std::borrow::Cow;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::{set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::{Config, SharedLogger};
+
+/// Last written record, with number of its suppressed repetitions
+struct LastRecord {
+    level: Level,
+    target: String,
+    module_path: Option<String>,
+    message: String,
+    started: Instant,
+    repeated: u64,
+}
+
+impl LastRecord {
+    /// Takes summary of suppressed repetitions, if there are any
+    fn take_summary(&mut self) -> Option<Summary> {
+        if self.repeated == 0 {
+            return None;
+        }
+        Some(Summary {
+            level: self.level,
+            target: self.target.clone(),
+            module_path: self.module_path.clone(),
+            message: self.message.clone(),
+            repeated: std::mem::take(&mut self.repeated),
+        })
+    }
+}
+
+/// Summary of suppressed repetitions, written after lock of last record is released
+struct Summary {
+    level: Level,
+    target: String,
+    module_path: Option<String>,
+    message: String,
+    repeated: u64,
+}
+
+/// Logger wrapper, which collapses consecutive identical records(same level, target and message)
+/// Only the first record is written, and `<message> (repeated N more times)` record with the same
+/// level and target is written instead of suppressed ones, when different record arrives, when any
+/// record passed to wrapper, even rejected by inner logger, comes after time window, or when logger
+/// is flushed
+/// Summary contains original message, so message filter of inner logger, which accepted it, usually
+/// accepts summary too
+/// There is no timer, so without further records or flush, summary is not written
+pub struct DedupLogger {
+    logger: Box<dyn SharedLogger>,
+    window: Duration,
+    last: Mutex<Option<LastRecord>>,
+}
+
+impl DedupLogger {
+    pub fn init(logger: Box<dyn SharedLogger>, window: Duration) -> Result<(), SetLoggerError> {
+        let logger = Self::new(logger, window);
+        set_max_level(logger.level());
+        set_boxed_logger(logger)
+    }
+
+    /// Creates wrapper, which suppresses repeated records for at most `window` since first of them
+    /// was written, so repeating record is still visible at least once per window
+    #[must_use]
+    pub fn new(logger: Box<dyn SharedLogger>, window: Duration) -> Box<Self> {
+        Box::new(Self {
+            logger,
+            window,
+            last: Mutex::new(None),
+        })
+    }
+
+    fn write_summary(&self, summary: Option<Summary>) {
+        let Some(summary) = summary else {
+            return;
+        };
+        let times = if summary.repeated == 1 { "time" } else { "times" };
+        self.logger.log(
+            &Record::builder()
+                .level(summary.level)
+                .target(&summary.target)
+                .module_path(summary.module_path.as_deref())
+                .args(format_args!("{} (repeated {} more {times})", summary.message, summary.repeated))
+                .build(),
+        );
+    }
+
+    fn take_summary(&self) -> Option<Summary> {
+        self.last.lock().unwrap().as_mut().and_then(LastRecord::take_summary)
+    }
+}
+
+impl Log for DedupLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            let summary = self.last.lock().unwrap().as_mut().filter(|last| last.started.elapsed() >= self.window).and_then(LastRecord::take_summary);
+            self.write_summary(summary);
+            return;
+        }
+
+        let message = match record.args().as_str() {
+            Some(message) => Cow::Borrowed(message),
+            None => Cow::Owned(record.args().to_string()),
+        };
+        let now = Instant::now();
+
+        // Lock is released before calling inner logger, so it may log through this wrapper again
+        let summary = {
+            let mut last = self.last.lock().unwrap();
+            if let Some(last) = &mut *last {
+                if last.level == record.level() && last.target == record.target() && last.message == message {
+                    if now.duration_since(last.started) < self.window {
+                        last.repeated += 1;
+                        return;
+                    }
+                }
+            }
+            let summary = last.as_mut().and_then(LastRecord::take_summary);
+            *last = Some(LastRecord {
+                level: record.level(),
+                target: record.target().to_string(),
+                module_path: record.module_path().map(str::to_string),
+                message: message.into_owned(),
+                started: now,
+                repeated: 0,
+            });
+            summary
+        };
+
+        self.write_summary(summary);
+        self.logger.log(record);
+    }
+
+    fn flush(&self) {
+        self.write_summary(self.take_summary());
+        self.logger.flush();
+    }
+}
+
+impl SharedLogger for DedupLogger {
+    fn level(&self) -> LevelFilter {
+        self.logger.level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        self.logger.config()
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.logger.set_level(level)
+    }
+
+    fn set_config(&mut self, config: Config) -> bool {
+        self.logger.set_config(config)
+    }
+
+    fn shutdown(&self) {
+        self.write_summary(self.take_summary());
+        self.logger.shutdown();
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, OnceLock};
+    use std::thread;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::WriteLogger;
+
+    use super::*;
+
+    fn dedup(buffer: &SharedBuffer, window: Duration) -> Box<DedupLogger> {
+        let config = test_config_builder().set_format_text("[[_level]] [_module] [_msg]", None).build();
+        DedupLogger::new(WriteLogger::new(config, buffer.clone()), window)
+    }
+
+    fn log_message(logger: &dyn Log, level: Level, target: &str, message: &str) {
+        logger.log(&Record::builder().level(level).target(target).module_path(Some(target)).args(format_args!("{message}")).build());
+    }
+
+    #[test]
+    fn test_repeated_records() {
+        let buffer = SharedBuffer::default();
+        let logger = dedup(&buffer, Duration::from_secs(60));
+
+        for _ in 0..5 {
+            log_message(&*logger, Level::Warn, "app::network", "Connection lost");
+        }
+        log_message(&*logger, Level::Error, "app::network", "Connection lost");
+        log_message(&*logger, Level::Error, "app::db", "Connection lost");
+        log_message(&*logger, Level::Error, "app::db", "Connection lost");
+        assert_eq!(
+            buffer.lines(),
+            [
+                "[WARN] app::network Connection lost",
+                "[WARN] app::network Connection lost (repeated 4 more times)",
+                "[ERROR] app::network Connection lost",
+                "[ERROR] app::db Connection lost"
+            ]
+        );
+
+        logger.flush();
+        assert_eq!(buffer.lines()[4..], ["[ERROR] app::db Connection lost (repeated 1 more time)"]);
+
+        // Record is still suppressed after flush, until window passes
+        log_message(&*logger, Level::Error, "app::db", "Connection lost");
+        log_message(&*logger, Level::Error, "app::db", "Connection lost");
+        logger.shutdown();
+        assert_eq!(buffer.lines()[5..], ["[ERROR] app::db Connection lost (repeated 2 more times)"]);
+    }
+
+    #[test]
+    fn test_window() {
+        let buffer = SharedBuffer::default();
+        let logger = dedup(&buffer, Duration::from_millis(200));
+
+        log_message(&*logger, Level::Info, "app", "Retrying");
+        log_message(&*logger, Level::Info, "app", "Retrying");
+        thread::sleep(Duration::from_millis(250));
+        log_message(&*logger, Level::Info, "app", "Retrying");
+        assert_eq!(buffer.lines(), ["[INFO] app Retrying", "[INFO] app Retrying (repeated 1 more time)", "[INFO] app Retrying"]);
+
+        // Filtered out record writes summary only after window passes
+        log_message(&*logger, Level::Info, "app", "Retrying");
+        log_message(&*logger, Level::Trace, "app", "Filtered out");
+        assert_eq!(buffer.lines().len(), 3);
+        thread::sleep(Duration::from_millis(250));
+        log_message(&*logger, Level::Trace, "app", "Filtered out");
+        assert_eq!(buffer.lines()[3..], ["[INFO] app Retrying (repeated 1 more time)"]);
+        logger.flush();
+        assert_eq!(buffer.lines().len(), 4);
+    }
+
+    #[test]
+    fn test_summary_passes_message_filter() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder()
+            .set_format_text("[_msg]", None)
+            .set_message_filtering(Some(|record: &Record| record.args().to_string().contains("Connection")))
+            .build();
+        let logger = DedupLogger::new(WriteLogger::new(config, buffer.clone()), Duration::from_secs(60));
+
+        log_message(&*logger, Level::Warn, "app", "Connection lost");
+        log_message(&*logger, Level::Warn, "app", "Connection lost");
+        logger.flush();
+        assert_eq!(buffer.lines(), ["Connection lost", "Connection lost (repeated 1 more time)"]);
+    }
+
+    /// Inner logger, which logs other record through outer wrapper, like logger reporting its own errors
+    struct ReentrantLogger {
+        logger: Box<dyn SharedLogger>,
+        outer: Arc<OnceLock<Arc<DedupLogger>>>,
+    }
+
+    impl Log for ReentrantLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            self.logger.enabled(metadata)
+        }
+
+        fn log(&self, record: &Record) {
+            self.logger.log(record);
+            if record.args().as_str() == Some("Disk full") {
+                log_message(&**self.outer.get().unwrap(), Level::Warn, "app", "Cleaning up");
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    impl SharedLogger for ReentrantLogger {
+        fn level(&self) -> LevelFilter {
+            self.logger.level()
+        }
+
+        fn config(&self) -> Option<&Config> {
+            self.logger.config()
+        }
+
+        fn as_log(self: Box<Self>) -> Box<dyn Log> {
+            Box::new(*self)
+        }
+    }
+
+    #[test]
+    fn test_reentrant_logging() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[[_level]] [_msg]", None).build();
+        let outer = Arc::new(OnceLock::new());
+        let inner = Box::new(ReentrantLogger { logger: WriteLogger::new(config, buffer.clone()), outer: outer.clone() });
+        let logger: Arc<DedupLogger> = DedupLogger::new(inner, Duration::from_secs(60)).into();
+        let _ = outer.set(logger.clone());
+
+        logger.log(&Record::builder().level(Level::Error).target("app").args(format_args!("Disk full")).build());
+        log_message(&*logger, Level::Warn, "app", "Cleaning up");
+        logger.flush();
+        assert_eq!(buffer.lines(), ["[ERROR] Disk full", "[WARN] Cleaning up", "[WARN] Cleaning up (repeated 1 more time)"]);
+    }
+}
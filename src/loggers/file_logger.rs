@@ -0,0 +1,467 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record};
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::{Duration, OffsetDateTime};
+
+use crate::common::get_env_log;
+use crate::compression::{with_suffix, Compressor, COMPRESSION_SUFFIXES};
+use crate::error::InitError;
+use crate::file_config::{fill_file_name_tokens, FileConfig};
+use crate::panic_hook::LogScope;
+use crate::reopen::{file_identity, install_sighup_handler, sighup_count, ReopenHandle};
+use crate::retention::apply_retention;
+use crate::{Config, LoggerGuard, SharedLogger};
+
+use super::logging::try_log;
+
+const ARCHIVE_TIME_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]_[hour]-[minute]-[second]");
+
+struct FileState {
+    file: Option<File>,
+    identity: Option<(u64, u64)>,
+    last_change_check: OffsetDateTime,
+    seen_sighup_count: usize,
+    size: u64,
+    started: OffsetDateTime,
+    next_rotation: Option<OffsetDateTime>,
+    buffer: Vec<u8>,
+}
+
+/// Logger that writes to file and rotates it when it grows too big or when rotation period passes
+/// Each record is formatted in memory first, so it is never split between two files
+pub struct FileLogger {
+    level: LevelFilter,
+    config: Config,
+    file_config: FileConfig,
+    path: PathBuf,
+    state: Mutex<FileState>,
+    compressor: Option<Compressor>,
+    reopen_handle: ReopenHandle,
+}
+
+impl FileLogger {
+    pub fn init<P: AsRef<Path>>(config: Config, path: P, file_config: FileConfig) -> Result<(), InitError> {
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        set_max_level(log_level);
+        let logger = Self::new(config, path, file_config)?;
+        set_boxed_logger(logger)?;
+        Ok(())
+    }
+
+    /// Sets logger as global logger and returns guard, which flushes file and waits until queued
+    /// archives are compressed when dropped
+    pub fn init_with_guard<P: AsRef<Path>>(config: Config, path: P, file_config: FileConfig) -> Result<LoggerGuard, InitError> {
+        Ok(LoggerGuard::init(Self::new(config, path, file_config)?)?)
+    }
+
+    /// Opens file at given path in append mode, creating it if it doesn't exist
+    /// If compression is enabled, starts compression thread, which also compresses archives
+    /// left uncompressed by previous run of application
+    pub fn new<P: AsRef<Path>>(mut config: Config, path: P, file_config: FileConfig) -> io::Result<Box<Self>> {
+        config.calculate_data();
+
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        let now = OffsetDateTime::now_utc().to_offset(config.time_offset);
+        let compressor = Compressor::start(path.as_ref(), file_config.archive_pattern, file_config.compression)?;
+        if file_config.reopen_on_sighup {
+            install_sighup_handler()?;
+        }
+        let logger = Self {
+            level: log_level,
+            config,
+            file_config,
+            path: path.as_ref().to_path_buf(),
+            state: Mutex::new(FileState {
+                file: None,
+                identity: None,
+                last_change_check: now,
+                seen_sighup_count: sighup_count(),
+                size: 0,
+                started: now,
+                next_rotation: None,
+                buffer: Vec::new(),
+            }),
+            compressor,
+            reopen_handle: ReopenHandle::default(),
+        };
+        logger.open(&mut logger.state.lock().unwrap(), now)?;
+
+        Ok(Box::new(logger))
+    }
+
+    /// Returns handle which allows to reopen log file from other place in application
+    /// e.g. from own signal handler
+    pub fn reopen_handle(&self) -> ReopenHandle {
+        self.reopen_handle.clone()
+    }
+
+    /// Opens log file and calculates when it should be rotated
+    /// Existing file keeps time of its last modification as start, so it is rotated
+    /// immediately if application was not running when its period ended
+    fn open(&self, state: &mut FileState, now: OffsetDateTime) -> io::Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let metadata = file.metadata()?;
+
+        state.size = metadata.len();
+        state.identity = file_identity(&metadata);
+        state.started = match metadata.modified() {
+            Ok(modified) if state.size > 0 => OffsetDateTime::from(modified).to_offset(self.config.time_offset).min(now),
+            _ => now,
+        };
+        state.next_rotation = self.file_config.rotation_period.map(|period| period.next_boundary(state.started));
+        state.file = Some(file);
+        Ok(())
+    }
+
+    /// Moves current file to archive, opens new one and removes archives exceeding retention limits
+    /// Empty files are not archived
+    fn rotate(&self, state: &mut FileState, now: OffsetDateTime) -> io::Result<()> {
+        state.file = None;
+        let renamed = if state.size > 0 {
+            let archive = archive_path(&self.path, self.file_config.archive_pattern, state.started);
+            let renamed = fs::rename(&self.path, &archive);
+            if let (Ok(()), Some(compressor)) = (&renamed, &self.compressor) {
+                compressor.compress(archive);
+            }
+            renamed
+        } else {
+            Ok(())
+        };
+        let opened = self.open(state, now);
+        let cleaned = apply_retention(&self.path, &self.file_config, state.size, SystemTime::now());
+        renamed.and(opened).and(cleaned)
+    }
+
+    fn rotate_if_needed(&self, state: &mut FileState, now: OffsetDateTime) -> io::Result<()> {
+        let record_size = state.buffer.len() as u64;
+        let size_exceeded = self.file_config.max_size.is_some_and(|max_size| state.size > 0 && state.size + record_size > max_size);
+        let period_passed = state.next_rotation.is_some_and(|next_rotation| now >= next_rotation);
+
+        if size_exceeded || period_passed {
+            self.rotate(state, now)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks if file should be reopened, because user requested it, SIGHUP was received or file at path changed
+    fn reopen_needed(&self, state: &mut FileState, now: OffsetDateTime) -> bool {
+        let mut reopen = self.reopen_handle.take_request();
+
+        if self.file_config.reopen_on_sighup {
+            let sighup_count = sighup_count();
+            reopen |= sighup_count != state.seen_sighup_count;
+            state.seen_sighup_count = sighup_count;
+        }
+
+        if self.file_config.reopen_on_file_change && now - state.last_change_check >= Duration::SECOND {
+            state.last_change_check = now;
+            let current_identity = fs::metadata(&self.path).ok().and_then(|metadata| file_identity(&metadata));
+            reopen |= current_identity.is_none() || current_identity != state.identity;
+        }
+
+        reopen
+    }
+
+    fn write_buffer(&self, state: &mut FileState, now: OffsetDateTime) -> io::Result<()> {
+        if self.reopen_needed(state, now) || state.file.is_none() {
+            state.file = None;
+            self.open(state, now)?;
+        }
+
+        // Even if rotation failed, record is still written to current file
+        let rotated = self.rotate_if_needed(state, now);
+
+        if let Some(file) = &mut state.file {
+            file.write_all(&state.buffer)?;
+            state.size += state.buffer.len() as u64;
+        }
+        rotated
+    }
+
+    fn log_at(&self, record: &Record, now: OffsetDateTime) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        state.buffer.clear();
+        if let Some(write_formatter) = &self.config.write_formatter {
+            let _ = write_formatter(record, &mut state.buffer);
+        } else {
+            let _ = try_log(&self.config, record, &mut state.buffer);
+        }
+        let _ = self.write_buffer(state, now);
+    }
+}
+
+/// Creates path of archive in the same directory as log file, which doesn't exist yet
+/// also in compressed form
+pub(crate) fn archive_path(path: &Path, pattern: &str, started: OffsetDateTime) -> PathBuf {
+    let name = fill_file_name_tokens(path, pattern).replace("[_time]", &started.format(ARCHIVE_TIME_FORMAT).unwrap_or_default());
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let has_index = name.contains("[_index]");
+    let candidate = |idx: usize| match (has_index, idx) {
+        (true, _) => directory.join(name.replace("[_index]", &idx.to_string())),
+        (false, 0) => directory.join(&name),
+        (false, _) => directory.join(format!("{name}.{idx}")),
+    };
+    (usize::from(has_index)..).map(candidate).find(|candidate| !archive_exists(candidate)).unwrap_or_default()
+}
+
+fn archive_exists(path: &Path) -> bool {
+    path.exists() || COMPRESSION_SUFFIXES.iter().any(|suffix| with_suffix(path, suffix).exists())
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
+        if let Some(message_filtering) = &self.config.message_filtering {
+            if !message_filtering(record) {
+                return;
+            }
+        }
+
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
+            self.log_at(record, OffsetDateTime::now_utc().to_offset(self.config.time_offset));
+        }
+    }
+
+    fn flush(&self) {
+        let _scope = LogScope::enter();
+        if let Some(file) = &mut self.state.lock().unwrap().file {
+            let _ = file.flush();
+        }
+    }
+}
+
+impl SharedLogger for FileLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        Some(&self.config)
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
+    /// Flushes file and waits until queued archives are compressed
+    fn shutdown(&self) {
+        self.flush();
+        if let Some(compressor) = &self.compressor {
+            compressor.finish();
+        }
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+    use time::macros::datetime;
+
+    use crate::common::TestDir;
+    use crate::file_config::RotationPeriod;
+    use crate::{ConfigBuilder, FileConfigBuilder};
+
+    use super::*;
+
+    fn log_message(logger: &FileLogger, message: &str, now: OffsetDateTime) {
+        logger.log_at(&Record::builder().level(Level::Info).args(format_args!("{message}")).build(), now);
+    }
+
+    fn read_files(dir: &TestDir) -> Vec<(String, String)> {
+        let mut files: Vec<_> = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                (path.file_name().unwrap().to_string_lossy().into_owned(), fs::read_to_string(&path).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_rotation_by_size_keeps_whole_records() {
+        let dir = TestDir::new("rotation_by_size");
+        let config = ConfigBuilder::new().set_format_text("[_msg]", None).build();
+        let file_config = FileConfigBuilder::new().set_max_size(Some(10)).set_archive_pattern("[_file_stem]-[_index].[_file_ext]").build();
+        let logger = FileLogger::new(config, dir.0.join("app.log"), file_config).unwrap();
+
+        let now = datetime!(2023-07-04 22:07:27 UTC);
+        for message in ["1234", "5678", "90", "abcdefghijklmno", "x"] {
+            log_message(&logger, message, now);
+        }
+
+        assert_eq!(
+            read_files(&dir),
+            vec![
+                ("app-1.log".to_string(), "1234\n5678\n".to_string()),
+                ("app-2.log".to_string(), "90\n".to_string()),
+                ("app-3.log".to_string(), "abcdefghijklmno\n".to_string()),
+                ("app.log".to_string(), "x\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rotation_removes_old_archives() {
+        let dir = TestDir::new("rotation_removes_old_archives");
+        let config = ConfigBuilder::new().set_format_text("[_msg]", None).build();
+        let file_config = FileConfigBuilder::new()
+            .set_max_size(Some(1))
+            .set_archive_pattern("[_file_stem]-[_index].[_file_ext]")
+            .set_max_archives(Some(1))
+            .build();
+        let logger = FileLogger::new(config, dir.0.join("app.log"), file_config).unwrap();
+
+        let now = datetime!(2023-07-04 22:07:27 UTC);
+        for message in ["a", "b", "c", "d"] {
+            log_message(&logger, message, now);
+        }
+
+        let files = read_files(&dir);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1], ("app.log".to_string(), "d\n".to_string()));
+    }
+
+    #[test]
+    fn test_rotation_by_time() {
+        let dir = TestDir::new("rotation_by_time");
+        let config = ConfigBuilder::new().set_format_text("[_msg]", None).set_remove_time_offset().build();
+        let file_config = FileConfigBuilder::new().set_rotation_period(Some(RotationPeriod::Hourly)).build();
+        let logger = FileLogger::new(config, dir.0.join("app.log"), file_config).unwrap();
+        // File was created now, so pretend that it was created at specific time
+        {
+            let mut state = logger.state.lock().unwrap();
+            state.started = datetime!(2023-07-04 22:07:27 UTC);
+            state.next_rotation = Some(RotationPeriod::Hourly.next_boundary(state.started));
+        }
+
+        log_message(&logger, "a", datetime!(2023-07-04 22:59:59 UTC));
+        log_message(&logger, "b", datetime!(2023-07-04 23:00:00 UTC));
+        log_message(&logger, "c", datetime!(2023-07-04 23:30:00 UTC));
+
+        let files = read_files(&dir);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0], ("app.log".to_string(), "b\nc\n".to_string()));
+        assert_eq!(files[1], ("app.log.2023-07-04_22-07-27".to_string(), "a\n".to_string()));
+    }
+
+    #[test]
+    fn test_reopen_with_handle() {
+        let dir = TestDir::new("reopen_with_handle");
+        let config = ConfigBuilder::new().set_format_text("[_msg]", None).build();
+        let logger = FileLogger::new(config, dir.0.join("app.log"), FileConfigBuilder::new().build()).unwrap();
+        let handle = logger.reopen_handle();
+
+        let now = datetime!(2023-07-04 22:07:27 UTC);
+        log_message(&logger, "a", now);
+        fs::rename(dir.0.join("app.log"), dir.0.join("app.log.old")).unwrap();
+        log_message(&logger, "b", now);
+        handle.reopen();
+        log_message(&logger, "c", now);
+
+        assert_eq!(
+            read_files(&dir),
+            vec![("app.log".to_string(), "c\n".to_string()), ("app.log.old".to_string(), "a\nb\n".to_string())]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reopen_on_file_change() {
+        let dir = TestDir::new("reopen_on_file_change");
+        let config = ConfigBuilder::new().set_format_text("[_msg]", None).build();
+        let file_config = FileConfigBuilder::new().set_reopen_on_file_change(true).build();
+        let logger = FileLogger::new(config, dir.0.join("app.log"), file_config).unwrap();
+
+        let now = OffsetDateTime::now_utc();
+        log_message(&logger, "a", now);
+        fs::rename(dir.0.join("app.log"), dir.0.join("app.log.old")).unwrap();
+        // Path is checked at most once per second
+        log_message(&logger, "b", now);
+        log_message(&logger, "c", now + Duration::SECOND);
+        fs::rename(dir.0.join("app.log"), dir.0.join("app.log.old2")).unwrap();
+        File::create(dir.0.join("app.log")).unwrap();
+        log_message(&logger, "d", now + Duration::SECOND * 2);
+
+        assert_eq!(
+            read_files(&dir),
+            vec![
+                ("app.log".to_string(), "d\n".to_string()),
+                ("app.log.old".to_string(), "a\nb\n".to_string()),
+                ("app.log.old2".to_string(), "c\n".to_string())
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reopen_on_sighup() {
+        let dir = TestDir::new("reopen_on_sighup");
+        let config = ConfigBuilder::new().set_format_text("[_msg]", None).build();
+        let file_config = FileConfigBuilder::new().set_reopen_on_sighup(true).build();
+        let logger = FileLogger::new(config, dir.0.join("app.log"), file_config).unwrap();
+
+        let now = datetime!(2023-07-04 22:07:27 UTC);
+        log_message(&logger, "a", now);
+        fs::rename(dir.0.join("app.log"), dir.0.join("app.log.old")).unwrap();
+        // SAFETY: SIGHUP handler is installed by logger, so process is not terminated
+        unsafe { libc::raise(libc::SIGHUP) };
+        log_message(&logger, "b", now);
+
+        assert_eq!(
+            read_files(&dir),
+            vec![("app.log".to_string(), "b\n".to_string()), ("app.log.old".to_string(), "a\n".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_rotation_period_boundaries() {
+        let time = datetime!(2023-07-04 22:07:27 +2);
+        assert_eq!(RotationPeriod::Hourly.next_boundary(time), datetime!(2023-07-04 23:00:00 +2));
+        assert_eq!(RotationPeriod::Daily.next_boundary(time), datetime!(2023-07-05 00:00:00 +2));
+    }
+
+    #[test]
+    fn test_archive_path_conflicts() {
+        let dir = TestDir::new("archive_path_conflicts");
+        let path = dir.0.join("app.log");
+        let time = datetime!(2023-07-04 22:07:27 UTC);
+
+        let archive = archive_path(&path, "[_file_name].[_time]", time);
+        assert_eq!(archive, dir.0.join("app.log.2023-07-04_22-07-27"));
+        File::create(&archive).unwrap();
+        let archive = archive_path(&path, "[_file_name].[_time]", time);
+        assert_eq!(archive, dir.0.join("app.log.2023-07-04_22-07-27.1"));
+        File::create(&archive).unwrap();
+        assert_eq!(archive_path(&path, "[_file_name].[_time]", time), dir.0.join("app.log.2023-07-04_22-07-27.2"));
+
+        assert_eq!(archive_path(&path, "[_file_stem]_[_index].[_file_ext]", time), dir.0.join("app_1.log"));
+    }
+}
@@ -0,0 +1,326 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+
+use log::kv::{self, Key, Source, Value, VisitSource};
+use log::{set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use super::logging::current_rust_thread_id;
+use crate::panic_hook::LogScope;
+use crate::record::OwnedRecord;
+use crate::{Config, SharedLogger};
+
+/// Text added before message of each record replayed by `FlightRecorder`
+const REPLAYED_PREFIX: &str = "[replayed] ";
+
+type Buffers = Mutex<HashMap<u64, VecDeque<OwnedRecord>>>;
+
+/// Buffers of recorders, which contain records of current thread
+/// When thread exits, its records are removed from them, so buffers of finished threads are not kept forever
+struct ThreadBuffers {
+    thread_id: u64,
+    buffers: Vec<Weak<Buffers>>,
+}
+
+impl ThreadBuffers {
+    fn register(&mut self, buffers: &Arc<Buffers>) {
+        self.buffers.retain(|registered| registered.strong_count() > 0);
+        if !self.buffers.iter().any(|registered| registered.as_ptr() == Arc::as_ptr(buffers)) {
+            self.buffers.push(Arc::downgrade(buffers));
+        }
+    }
+}
+
+impl Drop for ThreadBuffers {
+    fn drop(&mut self) {
+        for buffers in self.buffers.iter().filter_map(Weak::upgrade) {
+            buffers.lock().unwrap().remove(&self.thread_id);
+        }
+    }
+}
+
+thread_local! {
+    static THREAD_BUFFERS: RefCell<ThreadBuffers> = RefCell::new(ThreadBuffers {
+        thread_id: current_rust_thread_id(),
+        buffers: Vec::new(),
+    });
+}
+
+/// Which buffered records are replayed by `FlightRecorder` when error is logged
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub enum RecorderScope {
+    /// Each thread has its own buffer, error replays only records of thread which logged it
+    /// Buffer is removed when thread exits
+    #[default]
+    Thread,
+    /// All threads share one buffer, error replays records of all threads
+    Global,
+}
+
+/// Logger wrapper, which passes records with level up to `level` directly to inner logger, while
+/// more verbose records accepted by inner logger are silently kept in buffer
+/// When `Error` record arrives, last buffered records are written before it, so the context of
+/// failure is visible, without writing all verbose records all the time
+/// Replayed records have message prefixed with `[replayed] ` and additional key-value pair
+/// `replayed=true`
+pub struct FlightRecorder {
+    level: LevelFilter,
+    logger: Box<dyn SharedLogger>,
+    capacity: usize,
+    scope: RecorderScope,
+    buffers: Arc<Buffers>,
+}
+
+impl FlightRecorder {
+    pub fn init(logger: Box<dyn SharedLogger>, level: LevelFilter, capacity: usize, scope: RecorderScope) -> Result<(), SetLoggerError> {
+        let recorder = Self::new(logger, level, capacity, scope);
+        set_max_level(recorder.logger.level());
+        set_boxed_logger(recorder)
+    }
+
+    /// Creates wrapper, which writes records up to `level` and keeps at most `capacity` last more verbose
+    /// records, which are accepted by `logger`, so its level should be set e.g. to `Trace`
+    #[must_use]
+    pub fn new(logger: Box<dyn SharedLogger>, level: LevelFilter, capacity: usize, scope: RecorderScope) -> Box<Self> {
+        Box::new(Self {
+            level,
+            logger,
+            capacity,
+            scope,
+            buffers: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Level of records passed directly to inner logger, more verbose ones are buffered
+    #[must_use]
+    pub fn passthrough_level(&self) -> LevelFilter {
+        self.level
+    }
+
+    /// Changes level of records passed directly to inner logger
+    /// `SharedLogger::set_level` changes level of inner logger instead, which decides which records are
+    /// buffered at all
+    pub fn set_passthrough_level(&mut self, level: LevelFilter) {
+        self.level = level;
+    }
+
+    fn buffer_key(&self) -> u64 {
+        match self.scope {
+            RecorderScope::Thread => current_rust_thread_id(),
+            RecorderScope::Global => 0,
+        }
+    }
+
+    fn buffer(&self, record: &Record) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.scope == RecorderScope::Thread {
+            // Thread is exiting, so its records could never be replayed
+            if THREAD_BUFFERS.try_with(|thread_buffers| thread_buffers.borrow_mut().register(&self.buffers)).is_err() {
+                return;
+            }
+        }
+        let mut buffers = self.buffers.lock().unwrap();
+        let buffer = buffers.entry(self.buffer_key()).or_default();
+        if buffer.len() >= self.capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back(OwnedRecord::from_record(record));
+    }
+
+    /// Writes buffered records to inner logger, with original time and thread
+    fn replay_buffered(&self) {
+        let Some(records) = self.buffers.lock().unwrap().remove(&self.buffer_key()) else {
+            return;
+        };
+        for record in records {
+            record.replay(|record| {
+                let key_values = Replayed(record.key_values());
+                self.logger.log(
+                    &record
+                        .to_builder()
+                        .args(format_args!("{REPLAYED_PREFIX}{}", record.args()))
+                        .key_values(&key_values)
+                        .build(),
+                );
+            });
+        }
+    }
+}
+
+/// Key-value pairs of original record with added `replayed=true`
+struct Replayed<'a>(&'a dyn Source);
+
+impl Source for Replayed<'_> {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), kv::Error> {
+        self.0.visit(visitor)?;
+        visitor.visit_pair(Key::from_str("replayed"), Value::from(true))
+    }
+}
+
+impl Log for FlightRecorder {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if record.level() <= self.level {
+            if record.level() == Level::Error {
+                self.replay_buffered();
+            }
+            self.logger.log(record);
+        } else {
+            self.buffer(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.logger.flush();
+    }
+}
+
+impl SharedLogger for FlightRecorder {
+    fn level(&self) -> LevelFilter {
+        self.logger.level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        self.logger.config()
+    }
+
+    /// Changes level of inner logger, so also `level`, while level of records passed directly to it
+    /// is changed by `set_passthrough_level`
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.logger.set_level(level)
+    }
+
+    fn set_config(&mut self, config: Config) -> bool {
+        self.logger.set_config(config)
+    }
+
+    fn shutdown(&self) {
+        self.logger.shutdown();
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::{OutputFormat, WriteLogger};
+
+    use super::*;
+
+    fn recorder(buffer: &SharedBuffer, config: Config, scope: RecorderScope) -> Box<FlightRecorder> {
+        FlightRecorder::new(WriteLogger::new(config, buffer.clone()), LevelFilter::Info, 2, scope)
+    }
+
+    fn log_message(logger: &dyn Log, level: Level, message: &str) {
+        logger.log(&Record::builder().level(level).args(format_args!("{message}")).build());
+    }
+
+    fn text_config() -> Config {
+        test_config_builder().set_format_text("[[_level]] [_msg]", None).set_level(LevelFilter::Debug).build()
+    }
+
+    #[test]
+    fn test_replay_on_error() {
+        let buffer = SharedBuffer::default();
+        let recorder = recorder(&buffer, text_config(), RecorderScope::Thread);
+
+        log_message(&*recorder, Level::Debug, "First");
+        log_message(&*recorder, Level::Info, "Started");
+        log_message(&*recorder, Level::Debug, "Second");
+        log_message(&*recorder, Level::Trace, "Ignored by inner logger");
+        log_message(&*recorder, Level::Debug, "Third");
+        assert_eq!(buffer.lines(), ["[INFO] Started"]);
+
+        log_message(&*recorder, Level::Error, "Failed");
+        log_message(&*recorder, Level::Error, "Failed again");
+        assert_eq!(
+            buffer.lines(),
+            ["[INFO] Started", "[DEBUG] [replayed] Second", "[DEBUG] [replayed] Third", "[ERROR] Failed", "[ERROR] Failed again"]
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        for (scope, expected_replayed) in [(RecorderScope::Thread, 1), (RecorderScope::Global, 2)] {
+            let buffer = SharedBuffer::default();
+            let recorder = recorder(&buffer, text_config(), scope);
+
+            log_message(&*recorder, Level::Debug, "Main thread");
+            thread::scope(|scope| {
+                scope.spawn(|| log_message(&*recorder, Level::Debug, "Other thread"));
+            });
+            log_message(&*recorder, Level::Error, "Failed");
+
+            let lines = buffer.lines();
+            assert_eq!(lines.iter().filter(|line| line.contains(REPLAYED_PREFIX)).count(), expected_replayed, "{lines:?}");
+            assert!(lines.contains(&"[DEBUG] [replayed] Main thread".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_finished_threads_are_removed() {
+        let buffer = SharedBuffer::default();
+        let recorder = recorder(&buffer, text_config(), RecorderScope::Thread);
+
+        for _ in 0..10 {
+            // Joined explicitly, because scope may end before thread local values are dropped
+            thread::scope(|scope| {
+                let threads: Vec<_> = (0..10).map(|_| scope.spawn(|| log_message(&*recorder, Level::Debug, "Never replayed"))).collect();
+                threads.into_iter().for_each(|thread| thread.join().unwrap());
+            });
+        }
+        assert!(recorder.buffers.lock().unwrap().is_empty());
+
+        log_message(&*recorder, Level::Debug, "Main thread");
+        assert_eq!(recorder.buffers.lock().unwrap().len(), 1);
+        log_message(&*recorder, Level::Error, "Failed");
+        assert!(recorder.buffers.lock().unwrap().is_empty());
+        assert_eq!(buffer.lines(), ["[DEBUG] [replayed] Main thread", "[ERROR] Failed"]);
+    }
+
+    #[test]
+    fn test_replayed_key_value() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_output_format(OutputFormat::Logfmt).set_level(LevelFilter::Debug).build();
+        let recorder = recorder(&buffer, config, RecorderScope::Thread);
+
+        let key_values = [("user", "john")];
+        recorder.log(&Record::builder().level(Level::Debug).key_values(&key_values).args(format_args!("Context")).build());
+        log_message(&*recorder, Level::Error, "Failed");
+
+        let lines = buffer.lines();
+        assert!(lines[0].ends_with("msg=\"[replayed] Context\" user=john replayed=true"), "{lines:?}");
+        assert!(lines[1].ends_with("msg=Failed"), "{lines:?}");
+    }
+
+    #[test]
+    fn test_set_level() {
+        let buffer = SharedBuffer::default();
+        let mut recorder = recorder(&buffer, text_config(), RecorderScope::Thread);
+
+        assert!(recorder.set_level(LevelFilter::Trace));
+        assert_eq!(recorder.level(), LevelFilter::Trace);
+        assert_eq!(recorder.passthrough_level(), LevelFilter::Info);
+        log_message(&*recorder, Level::Trace, "Context");
+
+        recorder.set_passthrough_level(LevelFilter::Warn);
+        log_message(&*recorder, Level::Info, "More context");
+        log_message(&*recorder, Level::Error, "Failed");
+        assert_eq!(buffer.lines(), ["[TRACE] [replayed] Context", "[INFO] [replayed] More context", "[ERROR] Failed"]);
+    }
+}
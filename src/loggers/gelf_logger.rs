@@ -0,0 +1,446 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record};
+use time::OffsetDateTime;
+
+use crate::common::{get_env_log, syslog_severity};
+use crate::error::InitError;
+use crate::gelf_config::{GelfConfig, GELF_CHUNK_HEADER_SIZE};
+use crate::json::JsonObject;
+use crate::panic_hook::LogScope;
+use crate::record::{for_each_key_value, record_time};
+use crate::{Config, SharedLogger};
+
+use super::logging::try_log_message;
+
+/// GELF allows to send at most 128 chunks of one message
+const MAX_CHUNKS: usize = 128;
+
+/// Where `GelfLogger` sends messages
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GelfTransport {
+    /// Each message is sent in UDP datagram, split into chunks if it is too big
+    Udp(SocketAddr),
+    /// Messages are sent over TCP, separated by null byte
+    Tcp(SocketAddr),
+}
+
+enum Connection {
+    Udp(UdpSocket, SocketAddr),
+    Tcp(Option<TcpStream>, SocketAddr),
+}
+
+impl Connection {
+    fn open(transport: &GelfTransport) -> io::Result<Self> {
+        match transport {
+            GelfTransport::Udp(address) => {
+                let local_address: SocketAddr = if address.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+                Ok(Self::Udp(UdpSocket::bind(local_address)?, *address))
+            }
+            GelfTransport::Tcp(address) => Ok(Self::Tcp(Some(TcpStream::connect(address)?), *address)),
+        }
+    }
+
+    fn send(&mut self, message: &[u8], gelf_config: &GelfConfig) -> io::Result<()> {
+        match self {
+            Self::Udp(socket, address) => {
+                let message = compress(message, gelf_config)?;
+                if message.len() <= gelf_config.chunk_size {
+                    return socket.send_to(&message, *address).map(|_| ());
+                }
+                for chunk in split_into_chunks(&message, gelf_config.chunk_size, next_message_id())? {
+                    socket.send_to(&chunk, *address)?;
+                }
+                Ok(())
+            }
+            Self::Tcp(stream, address) => {
+                // Connection may be closed by server, so try to connect again once
+                let sent = match stream {
+                    Some(stream) => send_null_terminated(stream, message),
+                    None => Err(io::Error::from(io::ErrorKind::NotConnected)),
+                };
+                if sent.is_err() {
+                    *stream = None;
+                    let mut new_stream = TcpStream::connect(*address)?;
+                    send_null_terminated(&mut new_stream, message)?;
+                    *stream = Some(new_stream);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Sends message followed by null byte, which separates messages in GELF TCP
+fn send_null_terminated(stream: &mut TcpStream, message: &[u8]) -> io::Result<()> {
+    let mut framed = Vec::with_capacity(message.len() + 1);
+    framed.extend_from_slice(message);
+    framed.push(0);
+    stream.write_all(&framed)
+}
+
+#[cfg(feature = "gzip")]
+fn compress<'a>(message: &'a [u8], gelf_config: &GelfConfig) -> io::Result<Cow<'a, [u8]>> {
+    use flate2::write::GzEncoder;
+
+    if !gelf_config.compressed {
+        return Ok(Cow::Borrowed(message));
+    }
+    let mut encoder = GzEncoder::new(Vec::with_capacity(message.len() / 2), flate2::Compression::default());
+    encoder.write_all(message)?;
+    Ok(Cow::Owned(encoder.finish()?))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn compress<'a>(message: &'a [u8], _gelf_config: &GelfConfig) -> io::Result<Cow<'a, [u8]>> {
+    Ok(Cow::Borrowed(message))
+}
+
+/// Id identifying chunks of one message, unique between processes and messages
+fn next_message_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = OffsetDateTime::now_utc().unix_timestamp_nanos() as u64;
+    nanos ^ counter.rotate_left(40) ^ (u64::from(process::id()) << 16)
+}
+
+/// Splits message into GELF chunks - magic bytes, message id, sequence number, sequence count and data
+fn split_into_chunks(message: &[u8], chunk_size: usize, message_id: u64) -> io::Result<Vec<Vec<u8>>> {
+    let data_size = chunk_size - GELF_CHUNK_HEADER_SIZE;
+    let count = message.len().div_ceil(data_size);
+    if count > MAX_CHUNKS {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "GELF message is too big to be sent over UDP"));
+    }
+    Ok(message
+        .chunks(data_size)
+        .enumerate()
+        .map(|(idx, data)| {
+            let mut chunk = Vec::with_capacity(GELF_CHUNK_HEADER_SIZE + data.len());
+            chunk.extend_from_slice(&[0x1e, 0x0f]);
+            chunk.extend_from_slice(&message_id.to_be_bytes());
+            chunk.extend_from_slice(&[idx as u8, count as u8]);
+            chunk.extend_from_slice(data);
+            chunk
+        })
+        .collect())
+}
+
+/// Additional fields, which are reserved by GELF or written by logger itself
+const RESERVED_FIELDS: [&str; 5] = ["_id", "_target", "_module", "_file", "_line"];
+
+/// Prefix of key-value fields, which would collide with reserved fields
+const COLLIDING_FIELD_PREFIX: &str = "_fields.";
+
+/// Converts key of key-value pair to name of GELF additional field, prefixed with underscore
+/// Only letters, digits, underscores, dots and dashes are allowed, reserved names like `_id` or
+/// `_target` are renamed to `_fields.id` and `_fields.target`
+fn additional_field_name(key: &str) -> String {
+    let mut name = String::with_capacity(key.len() + 1);
+    name.push('_');
+    name.extend(key.chars().map(|character| if character.is_ascii_alphanumeric() || matches!(character, '_' | '.' | '-') { character } else { '_' }));
+    if RESERVED_FIELDS.contains(&name.as_str()) {
+        name.replace_range(..1, COLLIDING_FIELD_PREFIX);
+    }
+    name
+}
+
+struct GelfState {
+    connection: Connection,
+    buffer: Vec<u8>,
+    message: Vec<u8>,
+}
+
+/// Logger that sends records to Graylog or other GELF server, over UDP or TCP
+/// Text of message is formatted with config, so usually it should contain only `[_msg]`
+/// Key-value pairs of record are sent as additional fields
+pub struct GelfLogger {
+    level: LevelFilter,
+    config: Config,
+    gelf_config: GelfConfig,
+    state: Mutex<GelfState>,
+}
+
+impl GelfLogger {
+    pub fn init(config: Config, gelf_config: GelfConfig, transport: &GelfTransport) -> Result<(), InitError> {
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        set_max_level(log_level);
+        let logger = Self::new(config, gelf_config, transport)?;
+        set_boxed_logger(logger)?;
+        Ok(())
+    }
+
+    /// Opens socket, in case of TCP also connects to server
+    pub fn new(mut config: Config, gelf_config: GelfConfig, transport: &GelfTransport) -> io::Result<Box<Self>> {
+        config.calculate_data();
+
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        Ok(Box::new(Self {
+            level: log_level,
+            config,
+            gelf_config,
+            state: Mutex::new(GelfState {
+                connection: Connection::open(transport)?,
+                buffer: Vec::new(),
+                message: Vec::new(),
+            }),
+        }))
+    }
+
+    /// Writes GELF 1.1 JSON message to buffer
+    /// First line of text is used as `short_message`, whole multi-line text as `full_message`
+    fn encode(&self, record: &Record, time: OffsetDateTime, text: &str, buffer: &mut Vec<u8>) -> io::Result<()> {
+        buffer.clear();
+        let mut object = JsonObject::new(buffer)?;
+        object.string("version", "1.1")?;
+        object.string("host", &self.gelf_config.host)?;
+        match text.split_once('\n') {
+            Some((short_message, _)) => {
+                object.string("short_message", short_message)?;
+                object.string("full_message", text)?;
+            }
+            None => object.string("short_message", text)?,
+        }
+        let millis = time.unix_timestamp_nanos() / 1_000_000;
+        object.key("timestamp")?;
+        write!(object.write, "{}.{:03}", millis.div_euclid(1000), millis.rem_euclid(1000))?;
+        object.number("level", syslog_severity(record.level()))?;
+        object.string("_target", record.target())?;
+        if let Some(module) = record.module_path() {
+            object.string("_module", module)?;
+        }
+        if let Some(file) = record.file() {
+            object.string("_file", file)?;
+        }
+        if let Some(line) = record.line() {
+            object.number("_line", line)?;
+        }
+
+        let mut result = Ok(());
+        for_each_key_value(record.key_values(), |key, value| {
+            if result.is_ok() {
+                result = object.value(&additional_field_name(key.as_str()), &value);
+            }
+        });
+        result?;
+        object.finish()
+    }
+
+    fn try_log(&self, record: &Record) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        state.message.clear();
+        if let Some(write_formatter) = &self.config.write_formatter {
+            write_formatter(record, &mut state.message)?;
+        } else {
+            try_log_message(&self.config, record, &mut state.message)?;
+        }
+        while state.message.last() == Some(&b'\n') {
+            state.message.pop();
+        }
+
+        self.encode(record, record_time(), &String::from_utf8_lossy(&state.message), &mut state.buffer)?;
+        state.connection.send(&state.buffer, &self.gelf_config)
+    }
+}
+
+impl Log for GelfLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
+        if let Some(message_filtering) = &self.config.message_filtering {
+            if !message_filtering(record) {
+                return;
+            }
+        }
+
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
+            let _ = self.try_log(record);
+        }
+    }
+
+    fn flush(&self) {
+        let _scope = LogScope::enter();
+        if let Connection::Tcp(Some(stream), _) = &mut self.state.lock().unwrap().connection {
+            let _ = stream.flush();
+        }
+    }
+}
+
+impl SharedLogger for GelfLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        Some(&self.config)
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use log::kv::Value;
+    use log::Level;
+    use time::macros::datetime;
+
+    use crate::common::test_config_builder;
+    use crate::GelfConfigBuilder;
+
+    use super::*;
+
+    fn test_config() -> Config {
+        test_config_builder().set_format_text("[_msg]", None).set_level(LevelFilter::Trace).build()
+    }
+
+    fn udp_server() -> UdpSocket {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        server
+    }
+
+    #[test]
+    fn test_encode() {
+        let gelf_config = GelfConfigBuilder::new().set_host("host").build();
+        let logger = GelfLogger::new(test_config(), gelf_config, &GelfTransport::Udp(SocketAddr::from(([127, 0, 0, 1], 9)))).unwrap();
+        let key_values = [
+            ("user", Value::from("john")),
+            ("id", Value::from(42)),
+            ("bad key", Value::from(true)),
+            ("target", Value::from("other")),
+            ("line", Value::from(7)),
+        ];
+        let record = Record::builder()
+            .level(Level::Warn)
+            .target("server")
+            .line(Some(12))
+            .key_values(&key_values)
+            .build();
+        let mut buffer = Vec::new();
+
+        logger.encode(&record, datetime!(2023-07-04 22:07:27.123 UTC), "First line\nSecond \"line\"", &mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "{\"version\":\"1.1\",\"host\":\"host\",\"short_message\":\"First line\",\"full_message\":\"First line\\nSecond \\\"line\\\"\",\
+             \"timestamp\":1688508447.123,\"level\":4,\"_target\":\"server\",\"_line\":12,\"_user\":\"john\",\"_fields.id\":42,\"_bad_key\":true,\
+             \"_fields.target\":\"other\",\"_fields.line\":7}"
+        );
+    }
+
+    #[test]
+    fn test_udp() {
+        let server = udp_server();
+        let logger = GelfLogger::new(test_config(), GelfConfigBuilder::new().build(), &GelfTransport::Udp(server.local_addr().unwrap())).unwrap();
+
+        logger.log(&Record::builder().level(Level::Error).args(format_args!("Broken")).build());
+
+        let mut buffer = [0; 2048];
+        let size = server.recv(&mut buffer).unwrap();
+        let message = String::from_utf8_lossy(&buffer[..size]);
+        assert!(message.starts_with("{\"version\":\"1.1\""), "{message}");
+        assert!(message.contains("\"short_message\":\"Broken\""), "{message}");
+        assert!(message.contains("\"level\":3"), "{message}");
+    }
+
+    #[test]
+    fn test_udp_chunking() {
+        let server = udp_server();
+        let gelf_config = GelfConfigBuilder::new().set_host("host").set_chunk_size(100).build();
+        let logger = GelfLogger::new(test_config(), gelf_config, &GelfTransport::Udp(server.local_addr().unwrap())).unwrap();
+        let long_message = "abcdefghij".repeat(50);
+
+        logger.log(&Record::builder().level(Level::Info).args(format_args!("{long_message}")).build());
+
+        let mut chunks = BTreeMap::new();
+        let mut message_ids = Vec::new();
+        let mut buffer = [0; 2048];
+        loop {
+            let size = server.recv(&mut buffer).unwrap();
+            let chunk = &buffer[..size];
+            assert!(size <= 100);
+            assert_eq!(chunk[..2], [0x1e, 0x0f]);
+            message_ids.push(chunk[2..10].to_vec());
+            chunks.insert(chunk[10], chunk[GELF_CHUNK_HEADER_SIZE..].to_vec());
+            if chunks.len() == chunk[11] as usize {
+                break;
+            }
+        }
+        message_ids.dedup();
+        assert_eq!(message_ids.len(), 1);
+
+        let message = String::from_utf8(chunks.into_values().flatten().collect()).unwrap();
+        assert!(message.starts_with("{\"version\":\"1.1\",\"host\":\"host\""), "{message}");
+        assert!(message.contains(&format!("\"short_message\":\"{long_message}\"")), "{message}");
+    }
+
+    #[test]
+    fn test_too_many_chunks() {
+        assert!(split_into_chunks(&[0; 2000], 20, 1).is_err());
+        assert_eq!(split_into_chunks(&[0; 1000], 1012, 1).unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_udp_compressed() {
+        use flate2::read::GzDecoder;
+
+        let server = udp_server();
+        let gelf_config = GelfConfigBuilder::new().set_compressed(true).build();
+        let logger = GelfLogger::new(test_config(), gelf_config, &GelfTransport::Udp(server.local_addr().unwrap())).unwrap();
+
+        logger.log(&Record::builder().level(Level::Info).args(format_args!("Compressed")).build());
+
+        let mut buffer = [0; 2048];
+        let size = server.recv(&mut buffer).unwrap();
+        assert_eq!(buffer[..2], [0x1f, 0x8b]);
+        let mut message = String::new();
+        GzDecoder::new(&buffer[..size]).read_to_string(&mut message).unwrap();
+        assert!(message.contains("\"short_message\":\"Compressed\""), "{message}");
+    }
+
+    #[test]
+    fn test_tcp_null_byte_framing() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let logger = GelfLogger::new(test_config(), GelfConfigBuilder::new().build(), &GelfTransport::Tcp(server.local_addr().unwrap())).unwrap();
+
+        logger.log(&Record::builder().level(Level::Info).args(format_args!("First")).build());
+        logger.log(&Record::builder().level(Level::Info).args(format_args!("Second")).build());
+        drop(logger);
+
+        let mut received = String::new();
+        server.accept().unwrap().0.read_to_string(&mut received).unwrap();
+        let messages: Vec<_> = received.split_terminator('\0').collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("\"short_message\":\"First\""), "{}", messages[0]);
+        assert!(messages[1].contains("\"short_message\":\"Second\""), "{}", messages[1]);
+    }
+}
@@ -0,0 +1,381 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record};
+
+use crate::common::{get_env_log, syslog_severity};
+use crate::error::InitError;
+use crate::journald_config::JournaldConfig;
+use crate::panic_hook::LogScope;
+use crate::record::for_each_key_value;
+use crate::{Config, SharedLogger};
+
+use super::logging::try_log_message;
+
+struct JournaldState {
+    payload: Vec<u8>,
+    message: Vec<u8>,
+}
+
+/// Logger that sends records with structured fields to systemd-journald, using its native protocol
+/// Text of `MESSAGE` field is formatted with config, so usually it should contain only `[_msg]`
+/// Key-value pairs of record are sent as additional fields with uppercased names, names of fields
+/// written by logger itself are prefixed with `FIELDS_`
+pub struct JournaldLogger {
+    level: LevelFilter,
+    config: Config,
+    journald_config: JournaldConfig,
+    socket: UnixDatagram,
+    state: Mutex<JournaldState>,
+}
+
+impl JournaldLogger {
+    pub fn init(config: Config, journald_config: JournaldConfig) -> Result<(), InitError> {
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        set_max_level(log_level);
+        let logger = Self::new(config, journald_config)?;
+        set_boxed_logger(logger)?;
+        Ok(())
+    }
+
+    pub fn new(mut config: Config, journald_config: JournaldConfig) -> io::Result<Box<Self>> {
+        config.calculate_data();
+
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        Ok(Box::new(Self {
+            level: log_level,
+            config,
+            journald_config,
+            socket: UnixDatagram::unbound()?,
+            state: Mutex::new(JournaldState {
+                payload: Vec::new(),
+                message: Vec::new(),
+            }),
+        }))
+    }
+
+    /// Creates datagram with all fields of record
+    fn build_payload(&self, record: &Record, message: &[u8], payload: &mut Vec<u8>) {
+        payload.clear();
+        add_field(payload, "PRIORITY", syslog_severity(record.level()).to_string().as_bytes());
+        add_field(payload, "MESSAGE", message);
+        add_field(payload, "SYSLOG_IDENTIFIER", self.journald_config.syslog_identifier.as_bytes());
+        add_field(payload, "TARGET", record.target().as_bytes());
+        if let Some(file) = record.file() {
+            add_field(payload, "CODE_FILE", file.as_bytes());
+        }
+        if let Some(line) = record.line() {
+            add_field(payload, "CODE_LINE", line.to_string().as_bytes());
+        }
+        if let Some(module_path) = record.module_path() {
+            add_field(payload, "CODE_MODULE", module_path.as_bytes());
+        }
+        for_each_key_value(record.key_values(), |key, value| {
+            if let Some(name) = field_name(key.as_str()) {
+                add_field(payload, &name, value.to_string().as_bytes());
+            }
+        });
+    }
+
+    fn try_log(&self, record: &Record) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        state.message.clear();
+        if let Some(write_formatter) = &self.config.write_formatter {
+            write_formatter(record, &mut state.message)?;
+        } else {
+            try_log_message(&self.config, record, &mut state.message)?;
+        }
+        while state.message.last() == Some(&b'\n') {
+            state.message.pop();
+        }
+
+        self.build_payload(record, &state.message, &mut state.payload);
+        send_payload(&self.socket, &self.journald_config.socket_path, &state.payload)
+    }
+}
+
+/// Fields written for every record, key-values with same names are prefixed to not duplicate them
+const RESERVED_FIELDS: [&str; 7] = ["PRIORITY", "MESSAGE", "SYSLOG_IDENTIFIER", "TARGET", "CODE_FILE", "CODE_LINE", "CODE_MODULE"];
+
+/// Prefix of key-value fields, which would collide with fields written by logger
+const COLLIDING_FIELD_PREFIX: &str = "FIELDS_";
+
+/// Appends field in journald export format
+/// Values containing new lines are written as binary data, prefixed with their length
+fn add_field(payload: &mut Vec<u8>, name: &str, value: &[u8]) {
+    payload.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value);
+    payload.push(b'\n');
+}
+
+/// Converts key of key-value pair to valid journald field name - only uppercase letters, digits and
+/// underscores, not starting with underscore or digit, at most 64 characters
+/// Names of fields written by logger are prefixed, so they are not duplicated
+fn field_name(key: &str) -> Option<String> {
+    let mut name: String = key
+        .chars()
+        .map(|character| if character.is_ascii_alphanumeric() { character.to_ascii_uppercase() } else { '_' })
+        .skip_while(|character| *character == '_' || character.is_ascii_digit())
+        .take(64)
+        .collect();
+    if RESERVED_FIELDS.contains(&name.as_str()) {
+        name.insert_str(0, COLLIDING_FIELD_PREFIX);
+    }
+    (!name.is_empty()).then_some(name)
+}
+
+/// Sends payload in datagram, or if it is too big, through sealed memory file
+fn send_payload(socket: &UnixDatagram, socket_path: &Path, payload: &[u8]) -> io::Result<()> {
+    match socket.send_to(payload, socket_path) {
+        Ok(_) => Ok(()),
+        Err(err) if matches!(err.raw_os_error(), Some(libc::EMSGSIZE | libc::ENOBUFS)) => send_with_memfd(socket, socket_path, payload),
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes payload into memory file, seals it and sends its descriptor to journald
+fn send_with_memfd(socket: &UnixDatagram, socket_path: &Path, payload: &[u8]) -> io::Result<()> {
+    // SAFETY: name is valid null terminated string
+    let fd = unsafe { libc::memfd_create(c"handsome_logger".as_ptr(), libc::MFD_ALLOW_SEALING | libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: descriptor was just created and is not owned by anything else
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(payload)?;
+
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    // SAFETY: descriptor is valid, because it is owned by file
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    send_fd(socket, socket_path, file.as_raw_fd())
+}
+
+/// Sends empty datagram with file descriptor attached as `SCM_RIGHTS` message
+fn send_fd(socket: &UnixDatagram, socket_path: &Path, fd: RawFd) -> io::Result<()> {
+    // SAFETY: all structures are zeroed before use, path length is checked and control buffer is
+    // aligned and big enough to contain one file descriptor
+    unsafe {
+        let mut address: libc::sockaddr_un = std::mem::zeroed();
+        address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let path = socket_path.as_os_str().as_bytes();
+        if path.len() >= address.sun_path.len() {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+        for (target, source) in address.sun_path.iter_mut().zip(path) {
+            *target = *source as libc::c_char;
+        }
+
+        let fd_size = size_of::<RawFd>() as libc::c_uint;
+        let mut control = [0u64; 4];
+        let control_size = libc::CMSG_SPACE(fd_size) as usize;
+        debug_assert!(control_size <= size_of_val(&control));
+
+        let mut message: libc::msghdr = std::mem::zeroed();
+        message.msg_name = (&raw mut address).cast();
+        message.msg_namelen = (size_of::<libc::sa_family_t>() + path.len() + 1) as libc::socklen_t;
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = control_size as _;
+
+        let header = libc::CMSG_FIRSTHDR(&raw const message);
+        (*header).cmsg_level = libc::SOL_SOCKET;
+        (*header).cmsg_type = libc::SCM_RIGHTS;
+        (*header).cmsg_len = libc::CMSG_LEN(fd_size) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(header).cast::<RawFd>(), fd);
+
+        if libc::sendmsg(socket.as_raw_fd(), &raw const message, libc::MSG_NOSIGNAL) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+impl Log for JournaldLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
+        if let Some(message_filtering) = &self.config.message_filtering {
+            if !message_filtering(record) {
+                return;
+            }
+        }
+
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
+            let _ = self.try_log(record);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for JournaldLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        Some(&self.config)
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, SeekFrom};
+    use std::time::Duration;
+
+    use log::kv::Value;
+    use log::Level;
+
+    use crate::common::{test_config_builder, TestDir};
+    use crate::JournaldConfigBuilder;
+
+    use super::*;
+
+    /// Parses datagram in journald export format into list of fields
+    fn parse_payload(mut payload: &[u8]) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        while !payload.is_empty() {
+            let line_end = payload.iter().position(|&byte| byte == b'\n').unwrap();
+            let line = std::str::from_utf8(&payload[..line_end]).unwrap();
+            if let Some((name, value)) = line.split_once('=') {
+                fields.push((name.to_string(), value.to_string()));
+                payload = &payload[line_end + 1..];
+            } else {
+                let rest = &payload[line_end + 1..];
+                let length = u64::from_le_bytes(rest[..8].try_into().unwrap()) as usize;
+                fields.push((line.to_string(), String::from_utf8(rest[8..8 + length].to_vec()).unwrap()));
+                payload = &rest[8 + length + 1..];
+            }
+        }
+        fields
+    }
+
+    fn start_server(dir: &TestDir) -> (UnixDatagram, JournaldConfig) {
+        let socket_path = dir.0.join("journal.socket");
+        let server = UnixDatagram::bind(&socket_path).unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let journald_config = JournaldConfigBuilder::new().set_socket_path(socket_path).set_syslog_identifier("app").build();
+        (server, journald_config)
+    }
+
+    #[test]
+    fn test_fields() {
+        let dir = TestDir::new("journald_fields");
+        let (server, journald_config) = start_server(&dir);
+        let config = test_config_builder().set_format_text("[_msg]", None).build();
+        let logger = JournaldLogger::new(config, journald_config).unwrap();
+
+        let key_values = [
+            ("user_id", Value::from(42)),
+            ("request.path", Value::from("/index")),
+            ("_private", Value::from(true)),
+            ("message", Value::from("other")),
+            ("syslog_identifier", Value::from("other_app")),
+        ];
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("server")
+                .module_path(Some("server::handler"))
+                .file(Some("src/handler.rs"))
+                .line(Some(34))
+                .key_values(&key_values)
+                .args(format_args!("First line\nSecond line"))
+                .build(),
+        );
+
+        let mut buffer = vec![0; 4096];
+        let size = server.recv(&mut buffer).unwrap();
+        assert_eq!(
+            parse_payload(&buffer[..size]),
+            vec![
+                ("PRIORITY".to_string(), "4".to_string()),
+                ("MESSAGE".to_string(), "First line\nSecond line".to_string()),
+                ("SYSLOG_IDENTIFIER".to_string(), "app".to_string()),
+                ("TARGET".to_string(), "server".to_string()),
+                ("CODE_FILE".to_string(), "src/handler.rs".to_string()),
+                ("CODE_LINE".to_string(), "34".to_string()),
+                ("CODE_MODULE".to_string(), "server::handler".to_string()),
+                ("USER_ID".to_string(), "42".to_string()),
+                ("REQUEST_PATH".to_string(), "/index".to_string()),
+                ("PRIVATE".to_string(), "true".to_string()),
+                ("FIELDS_MESSAGE".to_string(), "other".to_string()),
+                ("FIELDS_SYSLOG_IDENTIFIER".to_string(), "other_app".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_memfd_fallback() {
+        let dir = TestDir::new("journald_memfd");
+        let (server, journald_config) = start_server(&dir);
+        let client = UnixDatagram::unbound().unwrap();
+        let mut payload = Vec::new();
+        add_field(&mut payload, "MESSAGE", "a".repeat(100_000).as_bytes());
+
+        send_with_memfd(&client, &journald_config.socket_path, &payload).unwrap();
+
+        // SAFETY: buffers are zeroed and big enough for one descriptor
+        let fd = unsafe {
+            let mut control = [0u64; 4];
+            let mut message: libc::msghdr = std::mem::zeroed();
+            message.msg_control = control.as_mut_ptr().cast();
+            message.msg_controllen = size_of_val(&control) as _;
+            assert!(libc::recvmsg(server.as_raw_fd(), &raw mut message, 0) >= 0);
+            let header = libc::CMSG_FIRSTHDR(&raw const message);
+            assert!(!header.is_null());
+            assert_eq!((*header).cmsg_type, libc::SCM_RIGHTS);
+            std::ptr::read_unaligned(libc::CMSG_DATA(header).cast::<RawFd>())
+        };
+        // SAFETY: descriptor was received from socket and is owned only by this test
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut received = Vec::new();
+        file.read_to_end(&mut received).unwrap();
+        assert_eq!(received, payload);
+    }
+
+    #[test]
+    fn test_field_names() {
+        assert_eq!(field_name("user_id"), Some("USER_ID".to_string()));
+        assert_eq!(field_name("__12abc"), Some("ABC".to_string()));
+        assert_eq!(field_name("_"), None);
+        assert_eq!(field_name(&"a".repeat(100)).map(|name| name.len()), Some(64));
+        assert_eq!(field_name("message"), Some("FIELDS_MESSAGE".to_string()));
+        assert_eq!(field_name("code.line"), Some("FIELDS_CODE_LINE".to_string()));
+        assert_eq!(field_name("_priority"), Some("FIELDS_PRIORITY".to_string()));
+    }
+}
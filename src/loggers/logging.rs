@@ -1,10 +1,14 @@
 use std::io::{Error, Write};
 use std::{process, thread};
 
-use crate::config::{TimeFormat, Token};
+use crate::common::syslog_severity;
+use crate::config::{OutputFormat, ThreadIdKind, TimeFormat, Token};
+use crate::json::write_json;
+use crate::logfmt::write_logfmt;
+use crate::record::{record_time, with_replayed};
 use crate::Config;
 use log::Record;
-use termcolor::{BufferedStandardStream, WriteColor};
+use termcolor::{BufferedStandardStream, NoColor, WriteColor};
 use time::macros::format_description;
 
 /// Logging functionality for `WriteLogger` that can be used for any write target, even console.
@@ -14,11 +18,29 @@ pub fn try_log<W>(config: &Config, record: &Record, write: &mut W) -> Result<(),
 where
     W: Write + Sized,
 {
+    if config.systemd_priority_prefix {
+        write_systemd_priority(write, record)?;
+    }
+    try_log_message(config, record, write)
+}
+
+/// Writes record like `try_log`, but without systemd priority prefix, for loggers which send
+/// priority separately from message, like syslog or journald
+pub(crate) fn try_log_message<W>(config: &Config, record: &Record, write: &mut W) -> Result<(), Error>
+where
+    W: Write + Sized,
+{
+    match config.output_format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => return write_json(config, record, write),
+        OutputFormat::Logfmt => return write_logfmt(config, record, &mut NoColor::new(write), false),
+    }
+
     for token in &config.tokens[record.level() as usize] {
         match token {
             Token::Time => write_time(write, config, record)?,
             Token::Level => write!(write, "{}", record.level())?,
-            Token::ThreadId => write_thread_id(write)?,
+            Token::ThreadId => write_thread_id(write, config)?,
             Token::ThreadName => write_thread_name(write)?,
             Token::ProcessId => write!(write, "{}", process::id())?,
             Token::Module => write!(write, "{}", record.module_path().unwrap_or("<unknown>"))?,
@@ -40,11 +62,25 @@ where
 /// using tokens `ColorStart` and `ColorEnd`, that allows to write colors into terminal
 #[inline(always)]
 pub fn try_log_term(config: &Config, record: &Record, write: &mut BufferedStandardStream) -> Result<(), Error> {
+    if config.systemd_priority_prefix {
+        write_systemd_priority(write, record)?;
+    }
+    match config.output_format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            write_json(config, record, write)?;
+            return flush_record(config, write);
+        }
+        OutputFormat::Logfmt => {
+            write_logfmt(config, record, write, config.enabled_colors && !config.systemd_priority_prefix)?;
+            return flush_record(config, write);
+        }
+    }
     for token in &config.tokens[record.level() as usize] {
         match token {
             Token::Time => write_time(write, config, record)?,
             Token::Level => write!(write, "{}", record.level())?,
-            Token::ThreadId => write_thread_id(write)?,
+            Token::ThreadId => write_thread_id(write, config)?,
             Token::ThreadName => write_thread_name(write)?,
             Token::ProcessId => write!(write, "{}", process::id())?,
             Token::Module => write!(write, "{}", record.module_path().unwrap_or("<unknown>"))?,
@@ -59,23 +95,70 @@ pub fn try_log_term(config: &Config, record: &Record, write: &mut BufferedStanda
     }
     writeln!(write)?;
 
+    flush_record(config, write)
+}
+
+fn flush_record(config: &Config, write: &mut BufferedStandardStream) -> Result<(), Error> {
     // The log crate holds the logger as a `static mut`, which isn't dropped
     // at program exit: https://doc.rust-lang.org/reference/items/static-items.html
     // Sadly, this means we can't rely on the BufferedStandardStreams flushing
     // themselves on the way out, so to avoid the Case of the Missing 8k,
-    // flush each entry.
-    write.flush()
+    // flush each entry, unless user flushes logger at exit e.g. with `LoggerGuard`.
+    if config.flush_every_record {
+        write.flush()
+    } else {
+        Ok(())
+    }
+}
+
+thread_local! {
+    static RUST_THREAD_ID: u64 = rust_thread_id();
+    static NATIVE_THREAD_ID: u64 = native_thread_id();
+}
+
+/// Extracts number from Rust thread id, this is done only once per thread
+fn rust_thread_id() -> u64 {
+    // TODO, change this to simple `thread::current().id().as_u64()` when will be stabilized
+    let thread_id_string = format!("{:?}", thread::current().id());
+    thread_id_string.trim_start_matches("ThreadId(").trim_end_matches(')').parse().unwrap_or(0)
+}
+
+/// Gets kernel thread id, this is done only once per thread
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn native_thread_id() -> u64 {
+    // SAFETY: gettid takes no arguments and cannot fail
+    unsafe { libc::syscall(libc::SYS_gettid) as u64 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn native_thread_id() -> u64 {
+    rust_thread_id()
+}
+
+/// Rust id of current thread, cached after first call
+pub(crate) fn current_rust_thread_id() -> u64 {
+    RUST_THREAD_ID.with(|id| *id)
+}
+
+/// Kernel id of current thread, cached after first call
+pub(crate) fn current_native_thread_id() -> u64 {
+    NATIVE_THREAD_ID.with(|id| *id)
 }
 
-/// Writes local thread id and starts from 1
+/// Writes thread id of chosen kind
+/// Rust thread id starts from 1, native one is the same as kernel TID
 #[inline(always)]
-pub fn write_thread_id<W>(write: &mut W) -> Result<(), Error>
+pub fn write_thread_id<W>(write: &mut W, config: &Config) -> Result<(), Error>
 where
     W: Write + Sized,
 {
-    // TODO, change this to simple `thread::current().id().as_u64()` when will be stabilized
-    let thread_id_string = format!("{:?}", thread::current().id()).replace("ThreadId(", "").replace(')', "");
-    write!(write, "{thread_id_string}")?;
+    let thread_id = with_replayed(|replayed| match (config.thread_id_kind, replayed) {
+        (ThreadIdKind::Rust, Some(replayed)) => replayed.thread_id(),
+        (ThreadIdKind::Native, Some(replayed)) => replayed.native_thread_id(),
+        (ThreadIdKind::Rust, None) => current_rust_thread_id(),
+        (ThreadIdKind::Native, None) => current_native_thread_id(),
+    });
+    write!(write, "{thread_id}")?;
 
     Ok(())
 }
@@ -86,12 +169,10 @@ pub fn write_thread_name<W>(write: &mut W) -> Result<(), Error>
 where
     W: Write + Sized,
 {
-    match thread::current().name() {
-        Some(thread_name) => write!(write, "{thread_name}")?,
-        None => write!(write, "<unknown>")?,
-    }
-
-    Ok(())
+    with_replayed(|replayed| match replayed {
+        Some(replayed) => write!(write, "{}", replayed.thread_name().unwrap_or("<unknown>")),
+        None => write!(write, "{}", thread::current().name().unwrap_or("<unknown>")),
+    })
 }
 
 /// Writes file name with its extension
@@ -124,7 +205,7 @@ where
     use time::error::Format;
     use time::format_description::well_known::*;
 
-    let time = time::OffsetDateTime::now_utc().to_offset(config.time_offset);
+    let time = record_time().to_offset(config.time_offset);
     let res = match config.time_format[record.level() as usize] {
         TimeFormat::Rfc2822 => time.format_into(write, &Rfc2822),
         TimeFormat::Rfc3339 => time.format_into(write, &Rfc3339),
@@ -145,7 +226,7 @@ where
 /// Writes color to terminal output
 #[inline(always)]
 pub fn set_color(write: &mut BufferedStandardStream, config: &Config, record: &Record, color_start: bool) -> Result<(), Error> {
-    if config.enabled_colors {
+    if config.enabled_colors && !config.systemd_priority_prefix {
         if color_start {
             let color = &config.compiled_colors[record.level() as usize];
             write.set_color(color)?;
@@ -157,6 +238,16 @@ pub fn set_color(write: &mut BufferedStandardStream, config: &Config, record: &R
     Ok(())
 }
 
+/// Writes sd-daemon priority prefix e.g. `<4>` for warnings, understood by journald on stdout and stderr
+#[inline(always)]
+pub fn write_systemd_priority<W>(write: &mut W, record: &Record) -> Result<(), Error>
+where
+    W: Write + Sized,
+{
+    write!(write, "<{}>", syslog_severity(record.level()))?;
+    Ok(())
+}
+
 /// Writes args provided in time macro
 /// E.g. record.args() in info!("Print This") will contain one argument "Print This"
 #[inline(always)]
@@ -243,6 +334,57 @@ mod tests {
         assert_eq!(String::from_utf8(res_vec).unwrap().len(), "20:24:46.123\n".len());
     }
 
+    #[test]
+    fn test_thread_id_kinds() {
+        let i = vec![Token::ThreadId];
+        for kind in [ThreadIdKind::Rust, ThreadIdKind::Native] {
+            let mut config = ConfigBuilder::new().set_thread_id_kind(kind).build();
+            config.tokens = [i.clone(), i.clone(), i.clone(), i.clone(), i.clone(), i.clone()];
+            let record = Record::builder().build();
+
+            let mut res_vec = Vec::new();
+            try_log(&config, &record, &mut res_vec).unwrap();
+            let main_id = String::from_utf8(res_vec).unwrap().trim_end().parse::<u64>().unwrap();
+
+            let other_id = thread::spawn(move || {
+                let mut res_vec = Vec::new();
+                try_log(&config, &Record::builder().build(), &mut res_vec).unwrap();
+                String::from_utf8(res_vec).unwrap().trim_end().parse::<u64>().unwrap()
+            })
+            .join()
+            .unwrap();
+            assert_ne!(main_id, other_id);
+        }
+
+        #[cfg(target_os = "linux")]
+        assert_eq!(native_thread_id(), unsafe { libc::gettid() } as u64);
+    }
+
+    #[test]
+    fn test_systemd_priority() {
+        let expected = [(Level::Error, "<3>"), (Level::Warn, "<4>"), (Level::Info, "<6>"), (Level::Debug, "<7>"), (Level::Trace, "<7>")];
+        for (level, prefix) in expected {
+            let mut buffer = Vec::new();
+            write_systemd_priority(&mut buffer, &Record::builder().level(level).build()).unwrap();
+            assert_eq!(String::from_utf8(buffer).unwrap(), prefix);
+        }
+
+        let mut config = ConfigBuilder::new().set_format_text("[_level] [_msg]", None).set_enabled_colours(true).set_systemd_priority_prefix(true).build();
+        config.calculate_data();
+        for (level, expected) in [(Level::Error, "<3>ERROR Message\n"), (Level::Debug, "<7>DEBUG Message\n")] {
+            let mut buffer = Vec::new();
+            try_log(&config, &Record::builder().level(level).args(format_args!("Message")).build(), &mut buffer).unwrap();
+            let written = String::from_utf8(buffer).unwrap();
+            assert_eq!(written, expected);
+            assert!(!written.contains('\x1b'));
+        }
+
+        // Loggers which send priority separately don't write prefix
+        let mut buffer = Vec::new();
+        try_log_message(&config, &Record::builder().level(Level::Error).args(format_args!("Message")).build(), &mut buffer).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "ERROR Message\n");
+    }
+
     #[test]
     fn test_colours() {
         for level_filter in &[Level::Info, Level::Warn, Level::Error, Level::Debug, Level::Trace] {
@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use time::OffsetDateTime;
+
+use crate::common::get_env_log;
+use crate::record::OwnedRecord;
+use crate::{Config, SharedLogger};
+
+use super::logging::try_log;
+
+/// How many records `MemoryLogger` keeps, when limit is reached oldest records are removed
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum MemoryLimit {
+    /// Maximum number of records
+    Records(usize),
+    /// Maximum approximate number of bytes used by records
+    Bytes(usize),
+}
+
+/// Filter used to select records kept by `MemoryLogger`
+/// By default all records are selected
+#[derive(Clone, Debug, Default)]
+pub struct MemoryQuery {
+    level: Option<LevelFilter>,
+    target: Option<String>,
+    since: Option<OffsetDateTime>,
+    until: Option<OffsetDateTime>,
+}
+
+impl MemoryQuery {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects only records with level equal or more important than given one
+    pub fn set_level(&mut self, level: LevelFilter) -> &mut Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Selects only records with given target or targets of its submodules
+    /// E.g. `app::network` selects also `app::network::http`, but not `app::networking`
+    pub fn set_target(&mut self, target: &str) -> &mut Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// Selects only records logged at given time or later
+    pub fn set_since(&mut self, since: OffsetDateTime) -> &mut Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Selects only records logged before given time
+    pub fn set_until(&mut self, until: OffsetDateTime) -> &mut Self {
+        self.until = Some(until);
+        self
+    }
+
+    fn matches(&self, record: &OwnedRecord) -> bool {
+        self.level.is_none_or(|level| record.level() <= level)
+            && self.target.as_deref().is_none_or(|target| {
+                record.target().strip_prefix(target).is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            && self.since.is_none_or(|since| record.time() >= since)
+            && self.until.is_none_or(|until| record.time() < until)
+    }
+}
+
+struct MemoryBuffer {
+    records: VecDeque<OwnedRecord>,
+    size: usize,
+}
+
+struct Shared {
+    buffer: Mutex<MemoryBuffer>,
+    limit: MemoryLimit,
+}
+
+/// Handle which gives access to records kept by `MemoryLogger`, also after logger was moved
+/// into `CombinedLogger` or set as global logger
+#[derive(Clone)]
+pub struct MemoryHandle {
+    shared: Arc<Shared>,
+}
+
+impl MemoryHandle {
+    /// Copies all kept records, from oldest to newest
+    pub fn snapshot(&self) -> Vec<OwnedRecord> {
+        self.shared.buffer.lock().unwrap().records.iter().cloned().collect()
+    }
+
+    /// Copies kept records, which match query, from oldest to newest
+    pub fn query(&self, query: &MemoryQuery) -> Vec<OwnedRecord> {
+        self.shared.buffer.lock().unwrap().records.iter().filter(|record| query.matches(record)).cloned().collect()
+    }
+
+    /// Formats records which match query with given config, exactly like `WriteLogger` would do it
+    /// Records are printed with time and thread of original log call
+    pub fn render(&self, query: &MemoryQuery, mut config: Config) -> String {
+        config.calculate_data();
+        let mut buffer = Vec::new();
+        for record in self.query(query) {
+            record.replay(|record| {
+                let _ = match &config.write_formatter {
+                    Some(write_formatter) => write_formatter(record, &mut buffer),
+                    None => try_log(&config, record, &mut buffer),
+                };
+            });
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Number of kept records
+    pub fn len(&self) -> usize {
+        self.shared.buffer.lock().unwrap().records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all kept records
+    pub fn clear(&self) {
+        let mut buffer = self.shared.buffer.lock().unwrap();
+        buffer.records.clear();
+        buffer.size = 0;
+    }
+}
+
+/// Logger that keeps last records in memory, so they can be later shown e.g. in admin panel or
+/// attached to crash report
+pub struct MemoryLogger {
+    level: LevelFilter,
+    config: Config,
+    handle: MemoryHandle,
+}
+
+impl MemoryLogger {
+    /// Sets logger as global logger and returns handle to its records
+    pub fn init(config: Config, limit: MemoryLimit) -> Result<MemoryHandle, SetLoggerError> {
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        set_max_level(log_level);
+        let logger = Self::new(config, limit);
+        let handle = logger.handle();
+        set_boxed_logger(logger)?;
+        Ok(handle)
+    }
+
+    #[must_use]
+    pub fn new(mut config: Config, limit: MemoryLimit) -> Box<Self> {
+        config.calculate_data();
+
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        Box::new(Self {
+            level: log_level,
+            config,
+            handle: MemoryHandle {
+                shared: Arc::new(Shared {
+                    buffer: Mutex::new(MemoryBuffer {
+                        records: VecDeque::new(),
+                        size: 0,
+                    }),
+                    limit,
+                }),
+            },
+        })
+    }
+
+    /// Returns handle to records, which should be taken before logger is moved
+    pub fn handle(&self) -> MemoryHandle {
+        self.handle.clone()
+    }
+
+    fn push(&self, record: OwnedRecord) {
+        let shared = &self.handle.shared;
+        let mut buffer = shared.buffer.lock().unwrap();
+        buffer.size += record.size();
+        buffer.records.push_back(record);
+
+        loop {
+            let over_limit = match shared.limit {
+                MemoryLimit::Records(max_records) => buffer.records.len() > max_records,
+                MemoryLimit::Bytes(max_bytes) => buffer.size > max_bytes,
+            };
+            if !over_limit {
+                break;
+            }
+            let Some(removed) = buffer.records.pop_front() else {
+                break;
+            };
+            buffer.size -= removed.size();
+        }
+    }
+}
+
+impl Log for MemoryLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(message_filtering) = &self.config.message_filtering {
+            if !message_filtering(record) {
+                return;
+            }
+        }
+
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
+            self.push(OwnedRecord::from_record(record));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for MemoryLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        Some(&self.config)
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use crate::common::test_config_builder;
+
+    use super::*;
+
+    fn log_message(logger: &MemoryLogger, level: Level, target: &str, message: &str) {
+        logger.log(&Record::builder().level(level).target(target).args(format_args!("{message}")).build());
+    }
+
+    fn messages(records: &[OwnedRecord]) -> Vec<&str> {
+        records.iter().map(OwnedRecord::message).collect()
+    }
+
+    #[test]
+    fn test_record_limit() {
+        let logger = MemoryLogger::new(test_config_builder().set_level(LevelFilter::Trace).build(), MemoryLimit::Records(3));
+        let handle = logger.handle();
+        for idx in 0..5 {
+            log_message(&logger, Level::Info, "app", &format!("Message {idx}"));
+        }
+        assert_eq!(messages(&handle.snapshot()), ["Message 2", "Message 3", "Message 4"]);
+
+        handle.clear();
+        assert!(handle.is_empty());
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let logger = MemoryLogger::new(test_config_builder().build(), MemoryLimit::Bytes(3000));
+        let handle = logger.handle();
+        let message = "a".repeat(1000);
+        for _ in 0..5 {
+            log_message(&logger, Level::Info, "app", &message);
+        }
+        assert_eq!(handle.len(), 2);
+        assert!(handle.snapshot().iter().map(OwnedRecord::size).sum::<usize>() <= 3000);
+    }
+
+    #[test]
+    fn test_query_and_render() {
+        let logger = MemoryLogger::new(test_config_builder().set_level(LevelFilter::Debug).build(), MemoryLimit::Records(100));
+        let handle = logger.handle();
+        let start = OffsetDateTime::now_utc();
+        log_message(&logger, Level::Debug, "app::network", "Connecting");
+        log_message(&logger, Level::Error, "app::network::http", "Timeout");
+        log_message(&logger, Level::Warn, "app::networking", "Slow");
+        log_message(&logger, Level::Trace, "app", "Dropped by level");
+        log_message(&logger, Level::Info, "other", "Other");
+
+        let records = handle.query(MemoryQuery::new().set_target("app::network"));
+        assert_eq!(messages(&records), ["Connecting", "Timeout"]);
+        let records = handle.query(MemoryQuery::new().set_level(LevelFilter::Warn));
+        assert_eq!(messages(&records), ["Timeout", "Slow"]);
+        let records = handle.query(MemoryQuery::new().set_since(start).set_until(OffsetDateTime::now_utc() + time::Duration::seconds(1)));
+        assert_eq!(messages(&records), ["Connecting", "Timeout", "Slow", "Other"]);
+        assert!(handle.query(MemoryQuery::new().set_until(start)).is_empty());
+
+        let render_config = test_config_builder().set_format_text("[[_level]] [_msg]", None).build();
+        assert_eq!(handle.render(MemoryQuery::new().set_level(LevelFilter::Warn), render_config), "[ERROR] Timeout\n[WARN] Slow\n");
+    }
+}
@@ -1,10 +1,32 @@
-pub use self::combine_logger::CombinedLogger;
+pub use self::async_logger::{AsyncLogger, OverflowPolicy};
+pub use self::combine_logger::{CombinedHandle, CombinedLogger};
+pub use self::dedup_logger::DedupLogger;
+pub use self::file_logger::FileLogger;
+pub use self::flight_recorder::{FlightRecorder, RecorderScope};
+pub use self::gelf_logger::{GelfLogger, GelfTransport};
+#[cfg(target_os = "linux")]
+pub use self::journald_logger::JournaldLogger;
+pub use self::memory_logger::{MemoryHandle, MemoryLimit, MemoryLogger, MemoryQuery};
+pub use self::rate_limit_logger::{RateLimitKey, RateLimitLogger};
+pub use self::sampling_logger::SamplingLogger;
 pub use self::simple_logger::SimpleLogger;
+pub use self::syslog_logger::{SyslogLogger, SyslogTransport};
 pub use self::term_logger::{TermLogger, TerminalMode};
 pub use self::write_logger::WriteLogger;
 
+mod async_logger;
 mod combine_logger;
+mod dedup_logger;
+mod file_logger;
+mod flight_recorder;
+mod gelf_logger;
+#[cfg(target_os = "linux")]
+mod journald_logger;
 pub mod logging;
+mod memory_logger;
+mod rate_limit_logger;
+mod sampling_logger;
 mod simple_logger;
+mod syslog_logger;
 mod term_logger;
 mod write_logger;
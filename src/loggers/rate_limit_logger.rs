@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::{set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::{Config, SharedLogger};
+
+/// What records share one rate limit of `RateLimitLogger`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub enum RateLimitKey {
+    /// Each place in code(`file:line`) has its own limit
+    #[default]
+    Callsite,
+    /// Each target has its own limit
+    Target,
+}
+
+/// Token bucket of one key, with records suppressed since last written record
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    suppressed: u64,
+    level: Level,
+    target: String,
+}
+
+impl Bucket {
+    /// Takes summary of records suppressed since last written record, if there are any
+    fn take_summary(&mut self) -> Option<Summary> {
+        if self.suppressed == 0 {
+            return None;
+        }
+        Some(Summary {
+            level: self.level,
+            target: self.target.clone(),
+            suppressed: std::mem::take(&mut self.suppressed),
+        })
+    }
+}
+
+/// Summary of suppressed records, written after lock of buckets is released
+struct Summary {
+    level: Level,
+    target: String,
+    suppressed: u64,
+}
+
+/// Buckets of all keys, callsites with static file name are looked up without allocation
+#[derive(Default)]
+struct Buckets {
+    callsites: HashMap<(&'static str, u32), Bucket>,
+    named: HashMap<String, Bucket>,
+    cleaned: Option<Instant>,
+}
+
+/// Logger wrapper, which limits number of records written by each callsite or target, using token bucket
+/// At most `burst` records are written at once, then new ones are allowed with rate `burst` per `period`
+/// When limit lifts, `N records were suppressed by rate limit` record is written before next record,
+/// summaries of records still limited are written when logger is flushed or shut down
+pub struct RateLimitLogger {
+    logger: Box<dyn SharedLogger>,
+    key: RateLimitKey,
+    burst: f64,
+    tokens_per_second: f64,
+    period: Duration,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimitLogger {
+    pub fn init(logger: Box<dyn SharedLogger>, key: RateLimitKey, burst: u32, period: Duration) -> Result<(), SetLoggerError> {
+        let logger = Self::new(logger, key, burst, period);
+        set_max_level(logger.level());
+        set_boxed_logger(logger)
+    }
+
+    /// Creates wrapper, which allows `burst` records per `period` for each key
+    #[must_use]
+    pub fn new(logger: Box<dyn SharedLogger>, key: RateLimitKey, burst: u32, period: Duration) -> Box<Self> {
+        let burst = f64::from(burst.max(1));
+        Box::new(Self {
+            logger,
+            key,
+            burst,
+            tokens_per_second: burst / period.as_secs_f64().max(f64::MIN_POSITIVE),
+            period,
+            buckets: Mutex::new(Buckets::default()),
+        })
+    }
+
+    fn bucket<'a>(&self, buckets: &'a mut Buckets, record: &Record, now: Instant) -> &'a mut Bucket {
+        let new_bucket = || Bucket {
+            tokens: self.burst,
+            refilled: now,
+            suppressed: 0,
+            level: record.level(),
+            target: record.target().to_string(),
+        };
+        match (self.key, record.file_static()) {
+            (RateLimitKey::Callsite, Some(file)) => buckets.callsites.entry((file, record.line().unwrap_or(0))).or_insert_with(new_bucket),
+            (RateLimitKey::Callsite, None) => {
+                let name = format!("{}:{}", record.file().unwrap_or("<unknown>"), record.line().unwrap_or(0));
+                buckets.named.entry(name).or_insert_with(new_bucket)
+            }
+            (RateLimitKey::Target, _) => {
+                if !buckets.named.contains_key(record.target()) {
+                    buckets.named.insert(record.target().to_string(), new_bucket());
+                }
+                buckets.named.get_mut(record.target()).unwrap()
+            }
+        }
+    }
+
+    /// Adds tokens for time passed since last refill
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.tokens_per_second).min(self.burst);
+        bucket.refilled = now;
+    }
+
+    /// Removes buckets, which are full and have no suppressed records, so they are the same as new
+    /// ones, at most once per period, so keys used only once are not kept forever
+    fn remove_full_buckets(&self, buckets: &mut Buckets, now: Instant) {
+        if buckets.cleaned.is_some_and(|cleaned| now.duration_since(cleaned) < self.period) {
+            return;
+        }
+        buckets.cleaned = Some(now);
+        let keep = |bucket: &mut Bucket| {
+            self.refill(bucket, now);
+            bucket.suppressed > 0 || bucket.tokens < self.burst
+        };
+        buckets.callsites.retain(|_, bucket| keep(bucket));
+        buckets.named.retain(|_, bucket| keep(bucket));
+    }
+
+    fn write_summary(&self, summary: &Summary) {
+        let records = if summary.suppressed == 1 { "record was" } else { "records were" };
+        self.logger.log(
+            &Record::builder()
+                .level(summary.level)
+                .target(&summary.target)
+                .args(format_args!("{} {records} suppressed by rate limit", summary.suppressed))
+                .build(),
+        );
+    }
+
+    fn write_all_summaries(&self) {
+        let summaries: Vec<Summary> = {
+            let mut buckets = self.buckets.lock().unwrap();
+            let buckets = &mut *buckets;
+            buckets.callsites.values_mut().chain(buckets.named.values_mut()).filter_map(Bucket::take_summary).collect()
+        };
+        for summary in &summaries {
+            self.write_summary(summary);
+        }
+    }
+}
+
+impl Log for RateLimitLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let now = Instant::now();
+        // Lock is released before calling inner logger, so it may log through this wrapper again
+        let summary = {
+            let mut buckets = self.buckets.lock().unwrap();
+            self.remove_full_buckets(&mut buckets, now);
+            let bucket = self.bucket(&mut buckets, record, now);
+
+            self.refill(bucket, now);
+            if bucket.tokens < 1.0 {
+                bucket.suppressed += 1;
+                bucket.level = record.level();
+                return;
+            }
+            bucket.tokens -= 1.0;
+            bucket.take_summary()
+        };
+
+        if let Some(summary) = &summary {
+            self.write_summary(summary);
+        }
+        self.logger.log(record);
+    }
+
+    /// Writes summaries of records suppressed since last written ones, then flushes inner logger
+    fn flush(&self) {
+        self.write_all_summaries();
+        self.logger.flush();
+    }
+}
+
+impl SharedLogger for RateLimitLogger {
+    fn level(&self) -> LevelFilter {
+        self.logger.level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        self.logger.config()
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.logger.set_level(level)
+    }
+
+    fn set_config(&mut self, config: Config) -> bool {
+        self.logger.set_config(config)
+    }
+
+    /// Writes summaries of records suppressed since last written ones, then shuts down inner logger
+    fn shutdown(&self) {
+        self.write_all_summaries();
+        self.logger.shutdown();
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::WriteLogger;
+
+    use super::*;
+
+    fn limiter(buffer: &SharedBuffer, key: RateLimitKey, burst: u32, period: Duration) -> Box<RateLimitLogger> {
+        let config = test_config_builder().set_format_text("[[_level]] [_msg]", None).build();
+        RateLimitLogger::new(WriteLogger::new(config, buffer.clone()), key, burst, period)
+    }
+
+    fn log_message(logger: &dyn Log, target: &str, line: u32, message: &str) {
+        logger.log(&Record::builder().level(Level::Warn).target(target).file_static(Some("src/main.rs")).line(Some(line)).args(format_args!("{message}")).build());
+    }
+
+    #[test]
+    fn test_callsite_limit() {
+        let buffer = SharedBuffer::default();
+        let logger = limiter(&buffer, RateLimitKey::Callsite, 2, Duration::from_secs(60));
+
+        for idx in 0..5 {
+            log_message(&*logger, "app", 10, &format!("First {idx}"));
+        }
+        log_message(&*logger, "app", 20, "Second");
+        assert_eq!(buffer.lines(), ["[WARN] First 0", "[WARN] First 1", "[WARN] Second"]);
+
+        logger.flush();
+        assert_eq!(buffer.lines()[3..], ["[WARN] 3 records were suppressed by rate limit"]);
+        log_message(&*logger, "app", 10, "First 5");
+        logger.shutdown();
+        assert_eq!(buffer.lines()[4..], ["[WARN] 1 record was suppressed by rate limit"]);
+    }
+
+    #[test]
+    fn test_callsite_without_static_file() {
+        let buffer = SharedBuffer::default();
+        let logger = limiter(&buffer, RateLimitKey::Callsite, 1, Duration::from_secs(60));
+        let file = String::from("src/main.rs");
+        for _ in 0..3 {
+            logger.log(&Record::builder().level(Level::Warn).file(Some(&file)).line(Some(10)).args(format_args!("Message")).build());
+        }
+        logger.flush();
+        assert_eq!(buffer.lines(), ["[WARN] Message", "[WARN] 2 records were suppressed by rate limit"]);
+    }
+
+    #[test]
+    fn test_full_buckets_are_removed() {
+        let buffer = SharedBuffer::default();
+        let logger = limiter(&buffer, RateLimitKey::Target, 1, Duration::from_millis(100));
+
+        for idx in 0..10 {
+            log_message(&*logger, &format!("target{idx}"), 10, "Message");
+        }
+        log_message(&*logger, "target0", 10, "Suppressed");
+        assert_eq!(logger.buckets.lock().unwrap().named.len(), 10);
+
+        thread::sleep(Duration::from_millis(150));
+        log_message(&*logger, "other", 10, "Message");
+        // Only bucket with suppressed record and the new one are kept
+        assert_eq!(logger.buckets.lock().unwrap().named.len(), 2);
+        log_message(&*logger, "target0", 10, "After limit");
+        assert_eq!(buffer.lines()[11..], ["[WARN] 1 record was suppressed by rate limit", "[WARN] After limit"]);
+    }
+
+    #[test]
+    fn test_target_limit_lifts() {
+        let buffer = SharedBuffer::default();
+        let logger = limiter(&buffer, RateLimitKey::Target, 1, Duration::from_millis(200));
+
+        log_message(&*logger, "app", 10, "First");
+        log_message(&*logger, "app", 20, "Suppressed");
+        log_message(&*logger, "app", 30, "Suppressed");
+        log_message(&*logger, "other", 10, "Other");
+        thread::sleep(Duration::from_millis(250));
+        log_message(&*logger, "app", 40, "After limit");
+        assert_eq!(
+            buffer.lines(),
+            ["[WARN] First", "[WARN] Other", "[WARN] 2 records were suppressed by rate limit", "[WARN] After limit"]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::cell::Cell;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::{set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::{Config, SharedLogger};
+
+thread_local! {
+    static RANDOM_STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+}
+
+/// Fast xorshift random number generator, with separate state for each thread
+fn random_u64() -> u64 {
+    RANDOM_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        x
+    })
+}
+
+/// Logger wrapper, which randomly writes only part of verbose records, e.g. to keep some `Debug`
+/// and `Trace` records in production without writing all of them
+/// Records with level less verbose than `level` are always written
+/// `N records were suppressed by sampling` record is written before next written record and when
+/// logger is flushed or shut down
+pub struct SamplingLogger {
+    logger: Box<dyn SharedLogger>,
+    level: LevelFilter,
+    threshold: u64,
+    suppressed: AtomicU64,
+}
+
+impl SamplingLogger {
+    pub fn init(logger: Box<dyn SharedLogger>, level: LevelFilter, probability: f64) -> Result<(), SetLoggerError> {
+        let logger = Self::new(logger, level, probability);
+        set_max_level(logger.level());
+        set_boxed_logger(logger)
+    }
+
+    /// Creates wrapper, which writes records with `level` or more verbose with given probability,
+    /// from 0.0(none is written) to 1.0(all are written)
+    #[must_use]
+    pub fn new(logger: Box<dyn SharedLogger>, level: LevelFilter, probability: f64) -> Box<Self> {
+        let probability = probability.clamp(0.0, 1.0);
+        let threshold = if probability >= 1.0 { u64::MAX } else { (probability * u64::MAX as f64) as u64 };
+        Box::new(Self {
+            logger,
+            level,
+            threshold,
+            suppressed: AtomicU64::new(0),
+        })
+    }
+
+    /// Number of records not written because of sampling, since last call or last written summary
+    pub fn take_suppressed(&self) -> u64 {
+        self.suppressed.swap(0, Ordering::Relaxed)
+    }
+
+    /// Writes summary of records suppressed since last summary, if there are any
+    fn write_summary(&self) {
+        let suppressed = self.take_suppressed();
+        if suppressed == 0 {
+            return;
+        }
+        let records = if suppressed == 1 { "record was" } else { "records were" };
+        self.logger.log(
+            &Record::builder()
+                .level(self.level.to_level().unwrap_or(Level::Trace))
+                .target(module_path!())
+                .args(format_args!("{suppressed} {records} suppressed by sampling"))
+                .build(),
+        );
+    }
+
+    fn sampled_out(&self, record: &Record) -> bool {
+        if self.level == LevelFilter::Off || record.level() < self.level {
+            return false;
+        }
+        self.threshold != u64::MAX && random_u64() >= self.threshold
+    }
+}
+
+impl Log for SamplingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if self.sampled_out(record) {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.write_summary();
+        self.logger.log(record);
+    }
+
+    fn flush(&self) {
+        self.write_summary();
+        self.logger.flush();
+    }
+}
+
+impl SharedLogger for SamplingLogger {
+    fn level(&self) -> LevelFilter {
+        self.logger.level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        self.logger.config()
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.logger.set_level(level)
+    }
+
+    fn set_config(&mut self, config: Config) -> bool {
+        self.logger.set_config(config)
+    }
+
+    fn shutdown(&self) {
+        self.write_summary();
+        self.logger.shutdown();
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::WriteLogger;
+
+    use super::*;
+
+    fn count(buffer: &SharedBuffer, level: Level) -> usize {
+        let line = format!("[{level}] Message");
+        buffer.lines().iter().filter(|written| **written == line).count()
+    }
+
+    /// Number of suppressed records from written summaries and not yet summarized
+    fn suppressed(buffer: &SharedBuffer, logger: &SamplingLogger) -> usize {
+        let summarized: usize = buffer
+            .lines()
+            .iter()
+            .filter_map(|line| line.strip_suffix(" records were suppressed by sampling").or_else(|| line.strip_suffix(" record was suppressed by sampling")))
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        summarized + logger.take_suppressed() as usize
+    }
+
+    fn log_records(probability: f64, records: usize) -> (SharedBuffer, Box<SamplingLogger>) {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[[_level]] [_msg]", None).set_level(LevelFilter::Trace).build();
+        let logger = SamplingLogger::new(WriteLogger::new(config, buffer.clone()), LevelFilter::Debug, probability);
+        for _ in 0..records {
+            for level in [Level::Info, Level::Debug, Level::Trace] {
+                logger.log(&Record::builder().level(level).args(format_args!("Message")).build());
+            }
+        }
+        (buffer, logger)
+    }
+
+    #[test]
+    fn test_sampling() {
+        let (buffer, logger) = log_records(0.0, 100);
+        assert_eq!(count(&buffer, Level::Info), 100);
+        assert_eq!(count(&buffer, Level::Debug) + count(&buffer, Level::Trace), 0);
+        assert_eq!(suppressed(&buffer, &logger), 200);
+
+        let (buffer, logger) = log_records(1.0, 100);
+        assert_eq!(count(&buffer, Level::Debug) + count(&buffer, Level::Trace), 200);
+        assert_eq!(suppressed(&buffer, &logger), 0);
+
+        let (buffer, logger) = log_records(0.25, 10_000);
+        let written = count(&buffer, Level::Debug) + count(&buffer, Level::Trace);
+        assert!((4_000..6_000).contains(&written), "{written}");
+        assert_eq!(suppressed(&buffer, &logger), 20_000 - written);
+    }
+
+    #[test]
+    fn test_summary() {
+        let (buffer, logger) = log_records(0.0, 2);
+        assert_eq!(buffer.lines(), ["[INFO] Message", "[DEBUG] 2 records were suppressed by sampling", "[INFO] Message"]);
+
+        logger.log(&Record::builder().level(Level::Trace).args(format_args!("Message")).build());
+        logger.flush();
+        assert_eq!(buffer.lines()[3..], ["[DEBUG] 3 records were suppressed by sampling"]);
+        assert_eq!(logger.take_suppressed(), 0);
+
+        logger.log(&Record::builder().level(Level::Debug).args(format_args!("Message")).build());
+        logger.shutdown();
+        assert_eq!(buffer.lines()[4..], ["[DEBUG] 1 record was suppressed by sampling"]);
+    }
+}
@@ -7,6 +7,7 @@ use crate::{Config, SharedLogger};
 
 use super::logging::try_log;
 use crate::common::get_env_log;
+use crate::panic_hook::LogScope;
 
 pub struct SimpleLogger {
     level: LevelFilter,
@@ -16,7 +17,7 @@ pub struct SimpleLogger {
 
 impl SimpleLogger {
     pub fn init(config: Config) -> Result<(), SetLoggerError> {
-        let log_level = get_env_log().unwrap_or(config.level);
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
         set_max_level(log_level);
         let logger = Self::new(log_level, config);
         set_boxed_logger(logger)
@@ -26,7 +27,7 @@ impl SimpleLogger {
     pub fn new(log_level: LevelFilter, mut config: Config) -> Box<Self> {
         config.calculate_data();
 
-        let log_level = get_env_log().unwrap_or(log_level);
+        let log_level = get_env_log(config.env_var).unwrap_or(log_level);
 
         Box::new(Self {
             level: log_level,
@@ -38,17 +39,18 @@ impl SimpleLogger {
 
 impl Log for SimpleLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
     }
 
     fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
         if let Some(message_filtering) = &self.config.message_filtering {
             if !message_filtering(record) {
                 return;
             }
         }
 
-        if self.enabled(record.metadata()) {
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
             let _lock = self.output_lock.lock().unwrap();
 
             if record.level() == Level::Error {
@@ -78,6 +80,18 @@ impl SharedLogger for SimpleLogger {
         Some(&self.config)
     }
 
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
     fn as_log(self: Box<Self>) -> Box<dyn Log> {
         Box::new(*self)
     }
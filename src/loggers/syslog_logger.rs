@@ -0,0 +1,378 @@
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record};
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+use crate::common::{get_env_log, syslog_severity};
+use crate::error::InitError;
+use crate::panic_hook::LogScope;
+use crate::record::record_time;
+use crate::syslog_config::{SyslogConfig, SyslogFormat};
+use crate::{Config, SharedLogger};
+
+use super::logging::try_log_message;
+
+/// Where `SyslogLogger` sends messages
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SyslogTransport {
+    /// Unix datagram socket of local syslog daemon, usually `/dev/log`
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// Each message is sent in separate UDP datagram
+    Udp(SocketAddr),
+    /// Messages are sent over TCP with octet counting framing from RFC 6587
+    Tcp(SocketAddr),
+}
+
+impl SyslogTransport {
+    /// Socket of local syslog daemon - `/dev/log` on Linux, `/var/run/syslog` on macOS,
+    /// UDP port 514 on localhost on other platforms
+    pub fn local() -> Self {
+        #[cfg(target_os = "macos")]
+        {
+            Self::Unix(PathBuf::from("/var/run/syslog"))
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            Self::Unix(PathBuf::from("/dev/log"))
+        }
+        #[cfg(not(unix))]
+        {
+            Self::Udp(SocketAddr::from(([127, 0, 0, 1], 514)))
+        }
+    }
+}
+
+enum Connection {
+    #[cfg(unix)]
+    Unix(UnixDatagram, PathBuf),
+    Udp(UdpSocket, SocketAddr),
+    Tcp(Option<TcpStream>, SocketAddr),
+}
+
+impl Connection {
+    fn open(transport: &SyslogTransport) -> io::Result<Self> {
+        match transport {
+            #[cfg(unix)]
+            SyslogTransport::Unix(path) => Ok(Self::Unix(UnixDatagram::unbound()?, path.clone())),
+            SyslogTransport::Udp(address) => {
+                let local_address: SocketAddr = if address.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+                Ok(Self::Udp(UdpSocket::bind(local_address)?, *address))
+            }
+            SyslogTransport::Tcp(address) => Ok(Self::Tcp(Some(TcpStream::connect(address)?), *address)),
+        }
+    }
+
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(socket, path) => socket.send_to(message, path).map(|_| ()),
+            Self::Udp(socket, address) => socket.send_to(message, *address).map(|_| ()),
+            Self::Tcp(stream, address) => {
+                // Connection may be closed by server, so try to connect again once
+                let sent = match stream {
+                    Some(stream) => send_framed(stream, message),
+                    None => Err(io::Error::from(io::ErrorKind::NotConnected)),
+                };
+                if sent.is_err() {
+                    *stream = None;
+                    let mut new_stream = TcpStream::connect(*address)?;
+                    send_framed(&mut new_stream, message)?;
+                    *stream = Some(new_stream);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Sends message prefixed with its length, as described in RFC 6587
+fn send_framed(stream: &mut TcpStream, message: &[u8]) -> io::Result<()> {
+    let mut framed = Vec::with_capacity(message.len() + 8);
+    write!(framed, "{} ", message.len())?;
+    framed.extend_from_slice(message);
+    stream.write_all(&framed)
+}
+
+struct SyslogState {
+    connection: Connection,
+    buffer: Vec<u8>,
+    message: Vec<u8>,
+}
+
+/// Logger that sends records to syslog daemon, over unix socket, UDP or TCP
+/// Text of message is formatted with config, so usually it should contain only `[_msg]`
+/// because time, level and application name are added by syslog
+pub struct SyslogLogger {
+    level: LevelFilter,
+    config: Config,
+    syslog_config: SyslogConfig,
+    state: Mutex<SyslogState>,
+}
+
+impl SyslogLogger {
+    pub fn init(config: Config, syslog_config: SyslogConfig, transport: &SyslogTransport) -> Result<(), InitError> {
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        set_max_level(log_level);
+        let logger = Self::new(config, syslog_config, transport)?;
+        set_boxed_logger(logger)?;
+        Ok(())
+    }
+
+    /// Opens socket, in case of TCP also connects to server
+    pub fn new(mut config: Config, syslog_config: SyslogConfig, transport: &SyslogTransport) -> io::Result<Box<Self>> {
+        config.calculate_data();
+
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
+        Ok(Box::new(Self {
+            level: log_level,
+            config,
+            syslog_config,
+            state: Mutex::new(SyslogState {
+                connection: Connection::open(transport)?,
+                buffer: Vec::new(),
+                message: Vec::new(),
+            }),
+        }))
+    }
+
+    /// Writes whole syslog message with header to buffer
+    fn format_message(&self, record: &Record, time: OffsetDateTime, text: &[u8], buffer: &mut Vec<u8>) -> io::Result<()> {
+        let syslog_config = &self.syslog_config;
+        let priority = syslog_config.facility as u8 * 8 + syslog_severity(record.level());
+        let time = time.to_offset(self.config.time_offset);
+
+        buffer.clear();
+        match syslog_config.format {
+            SyslogFormat::Rfc3164 => {
+                write!(buffer, "<{priority}>")?;
+                time.format_into(buffer, format_description!("[month repr:short] [day padding:space] [hour]:[minute]:[second]"))
+                    .map_err(io::Error::other)?;
+                write!(buffer, " {} {}", syslog_config.hostname, syslog_config.app_name)?;
+                if let Some(pid) = syslog_config.pid {
+                    write!(buffer, "[{pid}]")?;
+                }
+                write!(buffer, ": ")?;
+            }
+            SyslogFormat::Rfc5424 => {
+                write!(buffer, "<{priority}>1 ")?;
+                // RFC 5424 allows at most 6 digits of fractional seconds
+                time.format_into(buffer, format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6]"))
+                    .map_err(io::Error::other)?;
+                if time.offset().is_utc() {
+                    write!(buffer, "Z")?;
+                } else {
+                    time.format_into(buffer, format_description!("[offset_hour sign:mandatory]:[offset_minute]")).map_err(io::Error::other)?;
+                }
+                let nil_if_empty = |text: &str| if text.is_empty() { "-".to_string() } else { text.replace(' ', "_") };
+                write!(buffer, " {} {} ", nil_if_empty(&syslog_config.hostname), nil_if_empty(&syslog_config.app_name))?;
+                match syslog_config.pid {
+                    Some(pid) => write!(buffer, "{pid}")?,
+                    None => write!(buffer, "-")?,
+                }
+                write!(buffer, " - - ")?;
+            }
+        }
+        buffer.extend_from_slice(text);
+        Ok(())
+    }
+
+    fn try_log(&self, record: &Record) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        state.message.clear();
+        if let Some(write_formatter) = &self.config.write_formatter {
+            write_formatter(record, &mut state.message)?;
+        } else {
+            try_log_message(&self.config, record, &mut state.message)?;
+        }
+        while state.message.last() == Some(&b'\n') {
+            state.message.pop();
+        }
+
+        self.format_message(record, record_time(), &state.message, &mut state.buffer)?;
+        state.connection.send(&state.buffer)
+    }
+}
+
+impl Log for SyslogLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
+        if let Some(message_filtering) = &self.config.message_filtering {
+            if !message_filtering(record) {
+                return;
+            }
+        }
+
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
+            let _ = self.try_log(record);
+        }
+    }
+
+    fn flush(&self) {
+        let _scope = LogScope::enter();
+        if let Connection::Tcp(Some(stream), _) = &mut self.state.lock().unwrap().connection {
+            let _ = stream.flush();
+        }
+    }
+}
+
+impl SharedLogger for SyslogLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        Some(&self.config)
+    }
+
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use log::Level;
+    use time::macros::datetime;
+
+    use crate::common::test_config_builder;
+    use crate::syslog_config::Facility;
+    use crate::SyslogConfigBuilder;
+
+    use super::*;
+
+    fn test_config() -> Config {
+        test_config_builder().set_format_text("[_msg]", None).set_level(LevelFilter::Trace).set_remove_time_offset().build()
+    }
+
+    fn log_message(logger: &SyslogLogger, level: Level, message: &str) {
+        logger.log(&Record::builder().level(level).args(format_args!("{message}")).build());
+    }
+
+    #[test]
+    fn test_formats() {
+        let record = Record::builder().level(Level::Warn).build();
+        let time = datetime!(2023-07-04 22:07:27.123 UTC);
+        let mut buffer = Vec::new();
+
+        let syslog_config = SyslogConfigBuilder::new().set_hostname("host").set_app_name("app").set_pid(Some(1234)).set_facility(Facility::Local3).build();
+        let logger = SyslogLogger::new(test_config(), syslog_config, &SyslogTransport::Udp(SocketAddr::from(([127, 0, 0, 1], 9)))).unwrap();
+        logger.format_message(&record, time, b"Hello", &mut buffer).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer), "<156>Jul  4 22:07:27 host app[1234]: Hello");
+
+        let syslog_config = SyslogConfigBuilder::new()
+            .set_format(SyslogFormat::Rfc5424)
+            .set_hostname("host")
+            .set_app_name("my app")
+            .set_pid(None)
+            .build();
+        let logger = SyslogLogger::new(test_config(), syslog_config, &SyslogTransport::Udp(SocketAddr::from(([127, 0, 0, 1], 9)))).unwrap();
+        logger.format_message(&record, time, b"Hello", &mut buffer).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer), "<12>1 2023-07-04T22:07:27.123000Z host my_app - - - Hello");
+    }
+
+    #[test]
+    fn test_rfc5424_time_precision() {
+        let record = Record::builder().level(Level::Info).build();
+        let mut buffer = Vec::new();
+        let syslog_config = SyslogConfigBuilder::new().set_format(SyslogFormat::Rfc5424).set_hostname("host").set_app_name("app").set_pid(None).build();
+
+        let logger = SyslogLogger::new(test_config(), syslog_config.clone(), &SyslogTransport::Udp(SocketAddr::from(([127, 0, 0, 1], 9)))).unwrap();
+        logger.format_message(&record, datetime!(2023-07-04 22:07:27.123456789 UTC), b"Hello", &mut buffer).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer), "<14>1 2023-07-04T22:07:27.123456Z host app - - - Hello");
+
+        let config = test_config_builder().set_format_text("[_msg]", None).set_time_offset(time::macros::offset!(+2)).build();
+        let logger = SyslogLogger::new(config, syslog_config, &SyslogTransport::Udp(SocketAddr::from(([127, 0, 0, 1], 9)))).unwrap();
+        logger.format_message(&record, datetime!(2023-07-04 22:07:27.000000001 UTC), b"Hello", &mut buffer).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer), "<14>1 2023-07-05T00:07:27.000000+02:00 host app - - - Hello");
+    }
+
+    #[test]
+    fn test_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let syslog_config = SyslogConfigBuilder::new().set_hostname("host").set_app_name("app").set_pid(Some(1)).build();
+        let logger = SyslogLogger::new(test_config(), syslog_config, &SyslogTransport::Udp(server.local_addr().unwrap())).unwrap();
+
+        log_message(&logger, Level::Error, "Broken");
+
+        let mut buffer = [0; 1024];
+        let size = server.recv(&mut buffer).unwrap();
+        let message = String::from_utf8_lossy(&buffer[..size]);
+        assert!(message.starts_with("<11>"), "{message}");
+        assert!(message.ends_with(" host app[1]: Broken"), "{message}");
+    }
+
+    #[test]
+    fn test_tcp_octet_framing() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let syslog_config = SyslogConfigBuilder::new().set_format(SyslogFormat::Rfc5424).set_hostname("host").set_app_name("app").set_pid(Some(1)).build();
+        let logger = SyslogLogger::new(test_config(), syslog_config, &SyslogTransport::Tcp(server.local_addr().unwrap())).unwrap();
+
+        log_message(&logger, Level::Info, "First");
+        log_message(&logger, Level::Info, "Second");
+        drop(logger);
+
+        let mut received = String::new();
+        server.accept().unwrap().0.read_to_string(&mut received).unwrap();
+        let mut messages = Vec::new();
+        let mut remaining = received.as_str();
+        while let Some((length, rest)) = remaining.split_once(' ') {
+            let length: usize = length.parse().unwrap();
+            messages.push(&rest[..length]);
+            remaining = &rest[length..];
+        }
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("<14>1 ") && messages[0].ends_with(" host app 1 - - First"), "{}", messages[0]);
+        assert!(messages[1].ends_with(" host app 1 - - Second"), "{}", messages[1]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket() {
+        let dir = crate::common::TestDir::new("syslog_unix_socket");
+        let socket_path = dir.0.join("log.sock");
+        let server = UnixDatagram::bind(&socket_path).unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let syslog_config = SyslogConfigBuilder::new().set_hostname("host").set_app_name("app").set_pid(None).build();
+        let logger = SyslogLogger::new(test_config(), syslog_config, &SyslogTransport::Unix(socket_path)).unwrap();
+
+        log_message(&logger, Level::Debug, "Details");
+
+        let mut buffer = [0; 1024];
+        let size = server.recv(&mut buffer).unwrap();
+        let message = String::from_utf8_lossy(&buffer[..size]);
+        assert!(message.starts_with("<15>"), "{message}");
+        assert!(message.ends_with(" host app: Details"), "{message}");
+    }
+}
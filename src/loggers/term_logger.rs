@@ -5,7 +5,8 @@ use log::{set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Re
 use termcolor::{BufferedStandardStream, ColorChoice};
 
 use crate::common::get_env_log;
-use crate::{Config, SharedLogger};
+use crate::panic_hook::LogScope;
+use crate::{Config, LoggerGuard, LoggerHandle, SharedLogger};
 
 use super::logging::*;
 
@@ -30,12 +31,22 @@ pub struct TermLogger {
 
 impl TermLogger {
     pub fn init(config: Config, mode: TerminalMode, color_choice: ColorChoice) -> Result<(), SetLoggerError> {
-        let log_level = get_env_log().unwrap_or(config.level);
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
         set_max_level(log_level);
         let logger = Self::new(config, mode, color_choice);
         set_boxed_logger(logger)
     }
 
+    /// Sets logger as global logger and returns handle, which allows to change its level and config
+    pub fn init_with_handle(config: Config, mode: TerminalMode, color_choice: ColorChoice) -> Result<LoggerHandle, SetLoggerError> {
+        LoggerHandle::init(Self::new(config, mode, color_choice))
+    }
+
+    /// Sets logger as global logger and returns guard, which flushes terminal when dropped
+    pub fn init_with_guard(config: Config, mode: TerminalMode, color_choice: ColorChoice) -> Result<LoggerGuard, SetLoggerError> {
+        LoggerGuard::init(Self::new(config, mode, color_choice))
+    }
+
     #[must_use]
     pub fn new(mut config: Config, mode: TerminalMode, color_choice: ColorChoice) -> Box<Self> {
         let streams = match mode {
@@ -55,7 +66,7 @@ impl TermLogger {
 
         config.calculate_data();
 
-        let log_level = get_env_log().unwrap_or(config.level);
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
 
         Box::new(Self {
             level: log_level,
@@ -70,14 +81,14 @@ impl TermLogger {
     }
 
     pub fn init_from_config(config: Config) -> Result<(), SetLoggerError> {
-        let log_level = get_env_log().unwrap_or(config.level);
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
         set_max_level(log_level);
         let logger = Self::new_from_config(config);
         set_boxed_logger(logger)
     }
 
     fn try_log(&self, record: &Record) -> Result<(), Error> {
-        if self.enabled(record.metadata()) {
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
             let mut streams = self.streams.lock().unwrap();
 
             if let Some(terminal_logger) = &self.config.terminal_formatter {
@@ -99,10 +110,11 @@ impl TermLogger {
 
 impl Log for TermLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
     }
 
     fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
         if let Some(message_filtering) = &self.config.message_filtering {
             if !message_filtering(record) {
                 return;
@@ -113,6 +125,7 @@ impl Log for TermLogger {
     }
 
     fn flush(&self) {
+        let _scope = LogScope::enter();
         let mut streams = self.streams.lock().unwrap();
         let _ = streams.out.flush();
         let _ = streams.err.flush();
@@ -128,6 +141,18 @@ impl SharedLogger for TermLogger {
         Some(&self.config)
     }
 
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
     fn as_log(self: Box<Self>) -> Box<dyn Log> {
         Box::new(*self)
     }
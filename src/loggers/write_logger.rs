@@ -4,7 +4,8 @@ use std::sync::Mutex;
 use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 
 use crate::common::get_env_log;
-use crate::{Config, SharedLogger};
+use crate::panic_hook::LogScope;
+use crate::{Config, LoggerGuard, SharedLogger};
 
 use super::logging::try_log;
 
@@ -16,17 +17,22 @@ pub struct WriteLogger<W: Write + Send + 'static> {
 
 impl<W: Write + Send + 'static> WriteLogger<W> {
     pub fn init(config: Config, writable: W) -> Result<(), SetLoggerError> {
-        let log_level = get_env_log().unwrap_or(config.level);
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
         set_max_level(log_level);
         let logger = Self::new(config, writable);
         set_boxed_logger(logger)
     }
 
+    /// Sets logger as global logger and returns guard, which flushes writer when dropped
+    pub fn init_with_guard(config: Config, writable: W) -> Result<LoggerGuard, SetLoggerError> {
+        LoggerGuard::init(Self::new(config, writable))
+    }
+
     #[must_use]
     pub fn new(mut config: Config, writable: W) -> Box<Self> {
         config.calculate_data();
 
-        let log_level = get_env_log().unwrap_or(config.level);
+        let log_level = get_env_log(config.env_var).unwrap_or(config.level);
         Box::new(Self {
             level: log_level,
             config,
@@ -37,17 +43,18 @@ impl<W: Write + Send + 'static> WriteLogger<W> {
 
 impl<W: Write + Send + 'static> Log for WriteLogger<W> {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        metadata.level() <= self.level && self.config.env_enabled(metadata)
     }
 
     fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
         if let Some(message_filtering) = &self.config.message_filtering {
             if !message_filtering(record) {
                 return;
             }
         }
 
-        if self.enabled(record.metadata()) {
+        if self.enabled(record.metadata()) && self.config.env_matches_message(record) {
             let mut write_lock = self.writable.lock().unwrap();
 
             if let Some(write_formatter) = &self.config.write_formatter {
@@ -69,6 +76,7 @@ impl<W: Write + Send + 'static> Log for WriteLogger<W> {
     }
 
     fn flush(&self) {
+        let _scope = LogScope::enter();
         let _ = self.writable.lock().unwrap().flush();
     }
 }
@@ -82,6 +90,18 @@ impl<W: Write + Send + 'static> SharedLogger for WriteLogger<W> {
         Some(&self.config)
     }
 
+    fn set_level(&mut self, level: LevelFilter) -> bool {
+        self.level = level;
+        true
+    }
+
+    fn set_config(&mut self, mut config: Config) -> bool {
+        config.calculate_data();
+        self.level = get_env_log(config.env_var).unwrap_or(config.level);
+        self.config = config;
+        true
+    }
+
     fn as_log(self: Box<Self>) -> Box<dyn Log> {
         Box::new(*self)
     }
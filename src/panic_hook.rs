@@ -0,0 +1,149 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::Cell;
+use std::panic::{self, PanicHookInfo};
+use std::sync::Once;
+use std::thread;
+
+use log::{logger, Level, Record};
+
+/// Target of records logged by panic hook
+const PANIC_TARGET: &str = "panic";
+
+thread_local! {
+    static IN_LOG: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks current thread as running inside logger until dropped
+/// Panic raised there, e.g. by `Display` of logged value, is not logged by panic hook, because
+/// logger may still hold its locks, so logging again would deadlock
+pub(crate) struct LogScope {
+    outer: bool,
+}
+
+impl LogScope {
+    pub(crate) fn enter() -> Self {
+        Self { outer: IN_LOG.replace(true) }
+    }
+}
+
+impl Drop for LogScope {
+    fn drop(&mut self) {
+        IN_LOG.set(self.outer);
+    }
+}
+
+/// Installs panic hook, which logs panic at `Error` level through global logger and flushes it,
+/// before calling previously installed hook(by default the one printing panic to stderr)
+/// Record contains thread name, panic location and backtrace, when it is enabled by
+/// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variable
+/// Panics raised inside logger are not logged, only passed to previous hook
+/// Should be called after setting global logger, calling it multiple times installs hook only once
+pub fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            log_panic(info);
+            previous_hook(info);
+        }));
+    });
+}
+
+fn log_panic(info: &PanicHookInfo) {
+    if IN_LOG.get() {
+        return;
+    }
+    let thread = thread::current();
+    let thread_name = thread.name().unwrap_or("<unnamed>");
+    let payload = info.payload();
+    let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message,
+        (_, Some(message)) => message.as_str(),
+        _ => "Box<dyn Any>",
+    };
+    let backtrace = Backtrace::capture();
+
+    let location = info.location();
+    let mut builder = Record::builder();
+    builder.level(Level::Error).target(PANIC_TARGET).file(location.map(|location| location.file())).line(location.map(|location| location.line()));
+
+    let logger = logger();
+    match (location, backtrace.status()) {
+        (Some(location), BacktraceStatus::Captured) => {
+            logger.log(&builder.args(format_args!("thread '{thread_name}' panicked at {location}:\n{message}\nstack backtrace:\n{backtrace}")).build());
+        }
+        (Some(location), _) => logger.log(&builder.args(format_args!("thread '{thread_name}' panicked at {location}:\n{message}")).build()),
+        (None, BacktraceStatus::Captured) => {
+            logger.log(&builder.args(format_args!("thread '{thread_name}' panicked:\n{message}\nstack backtrace:\n{backtrace}")).build());
+        }
+        (None, _) => logger.log(&builder.args(format_args!("thread '{thread_name}' panicked:\n{message}")).build()),
+    }
+    logger.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use log::Log;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::{testing, WriteLogger};
+
+    use super::*;
+
+    struct PanickingDisplay;
+
+    impl fmt::Display for PanickingDisplay {
+        fn fmt(&self, _formatter: &mut fmt::Formatter) -> fmt::Result {
+            panic!("Display failed")
+        }
+    }
+
+    #[test]
+    fn test_panic_is_logged() {
+        install_panic_hook();
+        install_panic_hook();
+
+        let records = thread::Builder::new()
+            .name("worker".to_string())
+            .spawn(|| {
+                let logs = testing::capture();
+                let result = panic::catch_unwind(|| panic!("Invalid state {}", 5));
+                assert!(result.is_err());
+                logs.records()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.level(), Level::Error);
+        assert_eq!(record.target(), PANIC_TARGET);
+        assert_eq!(record.file(), Some(file!()));
+        assert!(record.line().is_some());
+        assert!(record.message().starts_with(&format!("thread 'worker' panicked at {}:", file!())), "{}", record.message());
+        assert!(record.message().contains(":\nInvalid state 5"), "{}", record.message());
+    }
+
+    #[test]
+    fn test_panic_inside_logger_is_not_logged() {
+        install_panic_hook();
+        let logger = WriteLogger::new(test_config_builder().build(), SharedBuffer::default());
+
+        let (message, records) = thread::spawn(move || {
+            let logs = testing::capture();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                logger.log(&Record::builder().level(Level::Error).args(format_args!("{}", PanickingDisplay)).build());
+            }));
+            (*result.unwrap_err().downcast::<&str>().unwrap(), logs.records())
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(message, "Display failed");
+        assert!(records.is_empty(), "{records:?}");
+    }
+}
@@ -0,0 +1,305 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::ptr;
+use std::thread;
+
+use log::kv::{self, Key, Source, ToValue, Value, VisitSource};
+use log::{Level, Record};
+use time::OffsetDateTime;
+
+use crate::loggers::logging::{current_native_thread_id, current_rust_thread_id};
+
+thread_local! {
+    static REPLAYED: Cell<*const OwnedRecord> = const { Cell::new(ptr::null()) };
+}
+
+/// Value of key-value pair, which keeps type of primitive values
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum OwnedValue {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Text(String),
+}
+
+impl OwnedValue {
+    fn from_value(value: &Value) -> Self {
+        if let Some(value) = value.to_bool() {
+            Self::Bool(value)
+        } else if let Some(value) = value.to_i64() {
+            Self::I64(value)
+        } else if let Some(value) = value.to_u64() {
+            Self::U64(value)
+        } else if let Some(value) = value.to_f64() {
+            Self::F64(value)
+        } else {
+            Self::Text(value.to_string())
+        }
+    }
+}
+
+impl ToValue for OwnedValue {
+    fn to_value(&self) -> Value<'_> {
+        match self {
+            Self::Bool(value) => Value::from(*value),
+            Self::I64(value) => Value::from(*value),
+            Self::U64(value) => Value::from(*value),
+            Self::F64(value) => Value::from(*value),
+            Self::Text(value) => Value::from(value.as_str()),
+        }
+    }
+}
+
+struct KeyValueVisitor<F>(F);
+
+impl<'kvs, F> VisitSource<'kvs> for KeyValueVisitor<F>
+where
+    F: FnMut(Key<'kvs>, Value<'kvs>),
+{
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        (self.0)(key, value);
+        Ok(())
+    }
+}
+
+/// Calls function for each key-value pair attached to record
+pub(crate) fn for_each_key_value<'kvs, F>(source: &'kvs dyn Source, f: F)
+where
+    F: FnMut(Key<'kvs>, Value<'kvs>),
+{
+    let _ = source.visit(&mut KeyValueVisitor(f));
+}
+
+/// Record which owns all its data, so it can be stored or sent to other thread
+/// Captures also time and thread of original log call, so it is printed the same
+/// way as if it was logged directly
+#[derive(Debug, Clone)]
+pub struct OwnedRecord {
+    level: Level,
+    target: String,
+    message: String,
+    module_path: Option<Cow<'static, str>>,
+    file: Option<Cow<'static, str>>,
+    line: Option<u32>,
+    time: OffsetDateTime,
+    thread_id: u64,
+    native_thread_id: u64,
+    thread_name: Option<String>,
+    key_values: Vec<(String, OwnedValue)>,
+}
+
+impl OwnedRecord {
+    /// Copies record data, time and info about current thread
+    /// If record is currently replayed, original time and thread are kept
+    pub fn from_record(record: &Record) -> Self {
+        let (time, thread_id, native_thread_id, thread_name) = with_replayed(|replayed| match replayed {
+            Some(replayed) => (replayed.time, replayed.thread_id, replayed.native_thread_id, replayed.thread_name.clone()),
+            None => (
+                OffsetDateTime::now_utc(),
+                current_rust_thread_id(),
+                current_native_thread_id(),
+                thread::current().name().map(str::to_string),
+            ),
+        });
+
+        let mut key_values = Vec::new();
+        for_each_key_value(record.key_values(), |key, value| key_values.push((key.to_string(), OwnedValue::from_value(&value))));
+
+        Self {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            module_path: owned_or_static(record.module_path_static(), record.module_path()),
+            file: owned_or_static(record.file_static(), record.file()),
+            line: record.line(),
+            time,
+            thread_id,
+            native_thread_id,
+            thread_name,
+            key_values,
+        }
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn module_path(&self) -> Option<&str> {
+        self.module_path.as_deref()
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Time of original log call, in UTC
+    pub fn time(&self) -> OffsetDateTime {
+        self.time
+    }
+
+    /// Rust id of thread which logged this record
+    pub fn thread_id(&self) -> u64 {
+        self.thread_id
+    }
+
+    /// Kernel id of thread which logged this record, on unsupported platforms equal to Rust id
+    pub fn native_thread_id(&self) -> u64 {
+        self.native_thread_id
+    }
+
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    /// Approximate number of bytes used by this record
+    pub fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.target.len()
+            + self.message.len()
+            + self.thread_name.as_ref().map_or(0, String::len)
+            + self
+                .key_values
+                .iter()
+                .map(|(key, value)| {
+                    size_of::<(String, OwnedValue)>()
+                        + key.len()
+                        + match value {
+                            OwnedValue::Text(text) => text.len(),
+                            _ => 0,
+                        }
+                })
+                .sum::<usize>()
+            + [&self.module_path, &self.file]
+                .into_iter()
+                .map(|item| match item {
+                    Some(Cow::Owned(text)) => text.len(),
+                    _ => 0,
+                })
+                .sum::<usize>()
+    }
+
+    /// Builds `Record` from this data and passes it to function
+    /// While function is running, loggers print time and thread of original log call
+    pub fn replay<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Record) -> R,
+    {
+        let _guard = ReplayGuard::new(self);
+
+        let mut builder = Record::builder();
+        builder.level(self.level).target(&self.target).line(self.line).key_values(&self.key_values);
+        match &self.module_path {
+            Some(Cow::Borrowed(module_path)) => builder.module_path_static(Some(module_path)),
+            module_path => builder.module_path(module_path.as_deref()),
+        };
+        match &self.file {
+            Some(Cow::Borrowed(file)) => builder.file_static(Some(file)),
+            file => builder.file(file.as_deref()),
+        };
+
+        f(&builder.args(format_args!("{}", self.message)).build())
+    }
+}
+
+fn owned_or_static(static_text: Option<&'static str>, text: Option<&str>) -> Option<Cow<'static, str>> {
+    match static_text {
+        Some(static_text) => Some(Cow::Borrowed(static_text)),
+        None => text.map(|text| Cow::Owned(text.to_string())),
+    }
+}
+
+/// Runs function with record that is currently replayed on this thread, if any
+pub(crate) fn with_replayed<F, R>(f: F) -> R
+where
+    F: FnOnce(Option<&OwnedRecord>) -> R,
+{
+    let replayed = REPLAYED.with(Cell::get);
+    // SAFETY: pointer is set only by `ReplayGuard`, which resets it before referenced record can be dropped
+    f(unsafe { replayed.as_ref() })
+}
+
+/// Time of currently logged record - time of original log call if record is replayed, otherwise current time
+pub(crate) fn record_time() -> OffsetDateTime {
+    with_replayed(|replayed| replayed.map_or_else(OffsetDateTime::now_utc, OwnedRecord::time))
+}
+
+struct ReplayGuard {
+    previous: *const OwnedRecord,
+}
+
+impl ReplayGuard {
+    fn new(record: &OwnedRecord) -> Self {
+        Self {
+            previous: REPLAYED.with(|replayed| replayed.replace(record)),
+        }
+    }
+}
+
+impl Drop for ReplayGuard {
+    fn drop(&mut self) {
+        REPLAYED.with(|replayed| replayed.set(self.previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_keeps_record_data() {
+        let owned = thread::Builder::new()
+            .name("original".to_string())
+            .spawn(|| {
+                let key_values = [("user", Value::from("john")), ("id", Value::from(42)), ("ratio", Value::from(0.5))];
+                OwnedRecord::from_record(
+                    &Record::builder()
+                        .level(Level::Warn)
+                        .target("target")
+                        .file(Some("file.rs"))
+                        .line(Some(12))
+                        .key_values(&key_values)
+                        .args(format_args!("Message {}", 1))
+                        .build(),
+                )
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        owned.replay(|record| {
+            assert_eq!(record.level(), Level::Warn);
+            assert_eq!(record.target(), "target");
+            assert_eq!(record.file(), Some("file.rs"));
+            assert_eq!(record.line(), Some(12));
+            assert_eq!(record.args().to_string(), "Message 1");
+
+            let mut key_values = Vec::new();
+            for_each_key_value(record.key_values(), |key, value| key_values.push((key.to_string(), OwnedValue::from_value(&value))));
+            assert_eq!(
+                key_values,
+                vec![
+                    ("user".to_string(), OwnedValue::Text("john".to_string())),
+                    ("id".to_string(), OwnedValue::I64(42)),
+                    ("ratio".to_string(), OwnedValue::F64(0.5))
+                ]
+            );
+
+            assert_eq!(record_time(), owned.time());
+            with_replayed(|replayed| assert_eq!(replayed.and_then(OwnedRecord::thread_name), Some("original")));
+        });
+        with_replayed(|replayed| assert!(replayed.is_none()));
+    }
+}
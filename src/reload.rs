@@ -0,0 +1,188 @@
+use std::sync::{Arc, RwLock};
+
+use log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::panic_hook::LogScope;
+use crate::{Config, LoggerGuard, SharedLogger};
+
+/// Global logger, which reads inner logger under lock, so it can be changed by `LoggerHandle`
+/// Lock is used instead of swapping pointer, because `set_level` and `set_config` change inner
+/// logger in place, readers share lock, so they don't block each other
+struct ReloadableLogger {
+    logger: Arc<RwLock<Box<dyn SharedLogger>>>,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.read().unwrap().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let _scope = LogScope::enter();
+        self.logger.read().unwrap().log(record);
+    }
+
+    fn flush(&self) {
+        let _scope = LogScope::enter();
+        self.logger.read().unwrap().flush();
+    }
+}
+
+impl SharedLogger for ReloadableLogger {
+    fn level(&self) -> LevelFilter {
+        self.logger.read().unwrap().level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn shutdown(&self) {
+        self.logger.read().unwrap().shutdown();
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+/// Handle which allows to change level and config of global logger at runtime, from any thread
+#[derive(Clone)]
+pub struct LoggerHandle {
+    logger: Arc<RwLock<Box<dyn SharedLogger>>>,
+    global: bool,
+}
+
+impl LoggerHandle {
+    /// Sets logger as global logger and returns handle to it
+    pub fn init(logger: Box<dyn SharedLogger>) -> Result<Self, SetLoggerError> {
+        let (mut handle, reloadable) = Self::new(logger);
+        handle.global = true;
+        set_max_level(handle.level());
+        set_boxed_logger(reloadable)?;
+        Ok(handle)
+    }
+
+    /// Sets logger as global logger and returns handle to it and guard, which shuts down current
+    /// logger when dropped
+    pub fn init_with_guard(logger: Box<dyn SharedLogger>) -> Result<(Self, LoggerGuard), SetLoggerError> {
+        let (mut handle, reloadable) = Self::new(logger);
+        handle.global = true;
+        let guard = LoggerGuard::init(reloadable)?;
+        Ok((handle, guard))
+    }
+
+    /// Creates handle and logger, which is not set as global logger, so `log::max_level` is not updated
+    fn new(logger: Box<dyn SharedLogger>) -> (Self, Box<ReloadableLogger>) {
+        let logger = Arc::new(RwLock::new(logger));
+        let handle = Self {
+            logger: logger.clone(),
+            global: false,
+        };
+        (handle, Box::new(ReloadableLogger { logger }))
+    }
+
+    /// Current level of logger
+    pub fn level(&self) -> LevelFilter {
+        self.logger.read().unwrap().level()
+    }
+
+    /// Changes level of logger and `log::max_level`
+    /// Returns false if logger or any of its inner loggers doesn't support changing level
+    pub fn set_level(&self, level: LevelFilter) -> bool {
+        self.update(|logger| logger.set_level(level))
+    }
+
+    /// Replaces config of logger, e.g. to change format, colors or filter, and updates `log::max_level`
+    /// Returns false if logger or any of its inner loggers doesn't support changing config
+    pub fn set_config(&self, config: Config) -> bool {
+        self.update(|logger| logger.set_config(config))
+    }
+
+    /// Replaces whole logger, e.g. to change terminal mode or output file
+    pub fn replace(&self, logger: Box<dyn SharedLogger>) {
+        let old_logger = self.update(|current| std::mem::replace(current, logger));
+        old_logger.flush();
+    }
+
+    fn update<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Box<dyn SharedLogger>) -> R,
+    {
+        let mut logger = self.logger.write().unwrap();
+        let result = f(&mut logger);
+        if self.global {
+            set_max_level(logger.level());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use crate::common::{test_config_builder, SharedBuffer};
+    use crate::{AsyncLogger, CombinedLogger, OverflowPolicy, WriteLogger};
+
+    use super::*;
+
+    fn log_message(logger: &dyn Log, level: Level, message: &str) {
+        logger.log(&Record::builder().level(level).args(format_args!("{message}")).build());
+    }
+
+    #[test]
+    fn test_reload_level_and_config() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[_msg]", None).set_level(LevelFilter::Info).build();
+        let (handle, logger) = LoggerHandle::new(CombinedLogger::new(vec![WriteLogger::new(config, buffer.clone())]));
+
+        log_message(&*logger, Level::Debug, "Hidden");
+        log_message(&*logger, Level::Info, "Visible");
+        assert_eq!(buffer.take(), "Visible\n");
+
+        assert!(handle.set_level(LevelFilter::Debug));
+        assert_eq!(handle.level(), LevelFilter::Debug);
+        log_message(&*logger, Level::Debug, "Debug");
+        assert_eq!(buffer.take(), "Debug\n");
+
+        let config = test_config_builder().set_format_text("[[_level]] [_msg]", None).set_level(LevelFilter::Warn).build();
+        assert!(handle.set_config(config));
+        assert_eq!(handle.level(), LevelFilter::Warn);
+        log_message(&*logger, Level::Info, "Hidden");
+        log_message(&*logger, Level::Warn, "Warning");
+        assert_eq!(buffer.take(), "[WARN] Warning\n");
+
+        let replaced_buffer = SharedBuffer::default();
+        handle.replace(WriteLogger::new(test_config_builder().set_format_text("new [_msg]", None).build(), replaced_buffer.clone()));
+        log_message(&*logger, Level::Info, "Message");
+        assert_eq!(buffer.take(), "");
+        assert_eq!(replaced_buffer.take(), "new Message\n");
+    }
+
+    #[test]
+    fn test_guard_shuts_down_current_logger() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[_msg]", None).build();
+        let (handle, reloadable) = LoggerHandle::new(WriteLogger::new(config, SharedBuffer::default()));
+        let (guard, logger) = LoggerGuard::new(reloadable);
+
+        let config = test_config_builder().set_format_text("[_msg]", None).build();
+        handle.replace(AsyncLogger::new(WriteLogger::new(config, buffer.clone()), 100, OverflowPolicy::Block));
+        for idx in 0..50 {
+            log_message(&*logger, Level::Info, &format!("Message {idx}"));
+        }
+        drop(guard);
+        assert_eq!(buffer.lines().len(), 50);
+        assert_eq!(buffer.lines().last().map(String::as_str), Some("Message 49"));
+    }
+
+    #[test]
+    fn test_unsupported_logger() {
+        let buffer = SharedBuffer::default();
+        let config = test_config_builder().set_format_text("[_msg]", None).build();
+        let (handle, _logger) = LoggerHandle::new(AsyncLogger::new(WriteLogger::new(config, buffer), 10, OverflowPolicy::Block));
+        assert!(!handle.set_level(LevelFilter::Trace));
+        assert_eq!(handle.level(), LevelFilter::Info);
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs::Metadata;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+static SIGHUP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Handle which allows to reopen log file of `FileLogger` e.g. after it was moved by external tool
+/// like `logrotate`
+#[derive(Debug, Clone, Default)]
+pub struct ReopenHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl ReopenHandle {
+    /// Requests reopening of log file, which is done before writing next record
+    pub fn reopen(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Identity of file on disk, which changes when file at path is replaced by other one
+#[cfg(unix)]
+pub(crate) fn file_identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn file_identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Number of SIGHUP signals received since handler was installed
+pub(crate) fn sighup_count() -> usize {
+    SIGHUP_COUNT.load(Ordering::Relaxed)
+}
+
+/// Handler which was installed before SIGHUP handler of logger, with its `sa_flags`
+#[cfg(unix)]
+static PREVIOUS_HANDLER: AtomicUsize = AtomicUsize::new(libc::SIG_DFL);
+#[cfg(unix)]
+static PREVIOUS_FLAGS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(unix)]
+extern "C" fn handle_sighup(signal: libc::c_int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    SIGHUP_COUNT.fetch_add(1, Ordering::Relaxed);
+    let previous = PREVIOUS_HANDLER.load(Ordering::Relaxed);
+    if previous == libc::SIG_DFL || previous == libc::SIG_IGN {
+        return;
+    }
+    if PREVIOUS_FLAGS.load(Ordering::Relaxed) as libc::c_int & libc::SA_SIGINFO != 0 {
+        // SAFETY: value was read from `sa_sigaction` of handler installed with `SA_SIGINFO`
+        let previous: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) = unsafe { std::mem::transmute(previous) };
+        previous(signal, info, context);
+    } else {
+        // SAFETY: value was read from `sa_sigaction` of handler installed without `SA_SIGINFO`
+        let previous: extern "C" fn(libc::c_int) = unsafe { std::mem::transmute(previous) };
+        previous(signal);
+    }
+}
+
+/// Installs SIGHUP handler once, previously installed handler is still called after it, no matter
+/// if it was installed with `SA_SIGINFO` or not
+#[cfg(unix)]
+pub(crate) fn install_sighup_handler() -> io::Result<()> {
+    use std::sync::OnceLock;
+
+    static INSTALLED: OnceLock<Result<(), i32>> = OnceLock::new();
+
+    let result = INSTALLED.get_or_init(|| {
+        // SAFETY: sigaction is called with zeroed structures, filled with valid handler
+        unsafe {
+            // Previous handler is stored before installing new one, so it is never skipped
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(libc::SIGHUP, std::ptr::null(), &raw mut previous) != 0 {
+                return Err(io::Error::last_os_error().raw_os_error().unwrap_or(0));
+            }
+            PREVIOUS_FLAGS.store(previous.sa_flags as usize, Ordering::Relaxed);
+            PREVIOUS_HANDLER.store(previous.sa_sigaction, Ordering::Relaxed);
+
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle_sighup as extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART | libc::SA_SIGINFO;
+            libc::sigemptyset(&raw mut action.sa_mask);
+            if libc::sigaction(libc::SIGHUP, &raw const action, std::ptr::null_mut()) != 0 {
+                return Err(io::Error::last_os_error().raw_os_error().unwrap_or(0));
+            }
+        }
+        Ok(())
+    });
+    (*result).map_err(io::Error::from_raw_os_error)
+}
+
+/// Signals are not available on this platform, so file can be only reopened with `ReopenHandle`
+#[cfg(not(unix))]
+pub(crate) fn install_sighup_handler() -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    static PLAIN_CALLS: AtomicUsize = AtomicUsize::new(0);
+    static SIGINFO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn plain_handler(_signal: libc::c_int) {
+        PLAIN_CALLS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    extern "C" fn siginfo_handler(_signal: libc::c_int, _info: *mut libc::siginfo_t, _context: *mut libc::c_void) {
+        SIGINFO_CALLS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_previous_handlers_are_chained() {
+        let (handler, flags) = (PREVIOUS_HANDLER.load(Ordering::Relaxed), PREVIOUS_FLAGS.load(Ordering::Relaxed));
+
+        PREVIOUS_HANDLER.store(siginfo_handler as extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) as usize, Ordering::Relaxed);
+        PREVIOUS_FLAGS.store(libc::SA_SIGINFO as usize, Ordering::Relaxed);
+        handle_sighup(libc::SIGHUP, std::ptr::null_mut(), std::ptr::null_mut());
+        assert_eq!(SIGINFO_CALLS.load(Ordering::Relaxed), 1);
+
+        PREVIOUS_HANDLER.store(plain_handler as extern "C" fn(libc::c_int) as usize, Ordering::Relaxed);
+        PREVIOUS_FLAGS.store(0, Ordering::Relaxed);
+        handle_sighup(libc::SIGHUP, std::ptr::null_mut(), std::ptr::null_mut());
+        assert_eq!(PLAIN_CALLS.load(Ordering::Relaxed), 1);
+        assert_eq!(SIGINFO_CALLS.load(Ordering::Relaxed), 1);
+
+        PREVIOUS_FLAGS.store(flags, Ordering::Relaxed);
+        PREVIOUS_HANDLER.store(handler, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,274 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::compression::{strip_archive_suffixes, TEMPORARY_SUFFIX};
+use crate::file_config::{fill_file_name_tokens, FileConfig};
+
+/// Archive of log file found on disk
+#[derive(Debug)]
+pub(crate) struct Archive {
+    pub(crate) path: PathBuf,
+    pub(crate) modified: SystemTime,
+    pub(crate) size: u64,
+}
+
+/// Part of archive name
+#[derive(Debug, PartialEq, Eq)]
+enum NamePart {
+    Text(String),
+    /// Time in format used by `FileLogger` for archives, e.g. `2023-07-04_22-07-27`
+    Time,
+    /// Non-empty sequence of digits
+    Index,
+}
+
+/// Shape of archive time, where `0` is any digit, must be kept in sync with `ARCHIVE_TIME_FORMAT`
+/// used by `FileLogger`
+const ARCHIVE_TIME_SHAPE: &[u8] = b"0000-00-00_00-00-00";
+
+/// Matches names of archives created from archive pattern
+/// Time token matches only archive time and index token only digits, so other files with
+/// similar names, like `app.log.bak`, are never treated as archives
+pub(crate) struct ArchiveMatcher {
+    parts: Vec<NamePart>,
+    log_file_name: Option<String>,
+}
+
+impl ArchiveMatcher {
+    pub(crate) fn new(path: &Path, pattern: &str) -> Self {
+        let filled = fill_file_name_tokens(path, pattern);
+        let mut name = filled.as_str();
+        let mut parts = Vec::new();
+        loop {
+            let time_idx = name.find("[_time]");
+            let index_idx = name.find("[_index]");
+            let (idx, part, token_len) = match (time_idx, index_idx) {
+                (Some(time_idx), Some(index_idx)) if index_idx < time_idx => (index_idx, NamePart::Index, "[_index]".len()),
+                (Some(time_idx), _) => (time_idx, NamePart::Time, "[_time]".len()),
+                (None, Some(index_idx)) => (index_idx, NamePart::Index, "[_index]".len()),
+                (None, None) => break,
+            };
+            if idx > 0 {
+                parts.push(NamePart::Text(name[..idx].to_string()));
+            }
+            parts.push(part);
+            name = &name[idx + token_len..];
+        }
+        if !name.is_empty() {
+            parts.push(NamePart::Text(name.to_string()));
+        }
+
+        Self {
+            parts,
+            log_file_name: path.file_name().map(|name| name.to_string_lossy().into_owned()),
+        }
+    }
+
+    /// Checks if file name matches pattern, also with `.N` suffix added when archive names conflict
+    /// and with compression suffix
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        self.matches_uncompressed(strip_archive_suffixes(name))
+    }
+
+    /// Checks if file name matches pattern, also with `.N` suffix added when archive names conflict
+    /// Live log file never matches, even if pattern would allow it
+    pub(crate) fn matches_uncompressed(&self, name: &str) -> bool {
+        if self.log_file_name.as_deref() == Some(name) {
+            return false;
+        }
+        if matches_parts(&self.parts, name) {
+            return true;
+        }
+        match name.rsplit_once('.') {
+            Some((name, suffix)) if !suffix.is_empty() && suffix.bytes().all(|byte| byte.is_ascii_digit()) => matches_parts(&self.parts, name),
+            _ => false,
+        }
+    }
+}
+
+fn matches_parts(parts: &[NamePart], name: &str) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return name.is_empty();
+    };
+    match part {
+        NamePart::Text(text) => name.strip_prefix(text.as_str()).is_some_and(|name| matches_parts(rest, name)),
+        NamePart::Time => {
+            let is_time = name.len() >= ARCHIVE_TIME_SHAPE.len()
+                && name.bytes().zip(ARCHIVE_TIME_SHAPE).all(|(byte, shape)| if *shape == b'0' { byte.is_ascii_digit() } else { byte == *shape });
+            is_time && matches_parts(rest, &name[ARCHIVE_TIME_SHAPE.len()..])
+        }
+        NamePart::Index => {
+            let digits = name.bytes().take_while(u8::is_ascii_digit).count();
+            (1..=digits).any(|len| matches_parts(rest, &name[len..]))
+        }
+    }
+}
+
+/// Finds all archives of log file, sorted from newest to oldest
+pub(crate) fn find_archives(path: &Path, pattern: &str) -> io::Result<Vec<Archive>> {
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let matcher = ArchiveMatcher::new(path, pattern);
+
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let lossy_name = name.to_string_lossy();
+        // Files that are being compressed right now are not complete archives yet
+        if lossy_name.ends_with(TEMPORARY_SUFFIX) || !matcher.matches(&lossy_name) {
+            continue;
+        }
+        // File may be already removed or renamed by compression thread
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let Ok(modified) = metadata.modified() else {
+            continue;
+        };
+        if metadata.is_file() {
+            archives.push(Archive {
+                path: entry.path(),
+                modified,
+                size: metadata.len(),
+            });
+        }
+    }
+    archives.sort_by(|first, second| second.modified.cmp(&first.modified).then_with(|| second.path.cmp(&first.path)));
+    Ok(archives)
+}
+
+/// Removes archives that exceed limits set in file config
+/// Current log file is never removed, but its size counts into total size limit
+pub(crate) fn apply_retention(path: &Path, file_config: &FileConfig, current_size: u64, now: SystemTime) -> io::Result<()> {
+    if file_config.max_archives.is_none() && file_config.max_archive_age.is_none() && file_config.max_total_size.is_none() {
+        return Ok(());
+    }
+
+    let mut total_size = current_size;
+    let mut result = Ok(());
+    for (idx, archive) in find_archives(path, file_config.archive_pattern)?.into_iter().enumerate() {
+        total_size += archive.size;
+
+        let too_many = file_config.max_archives.is_some_and(|max_archives| idx >= max_archives);
+        let too_old = file_config
+            .max_archive_age
+            .is_some_and(|max_age| now.duration_since(archive.modified).unwrap_or(Duration::ZERO) > max_age);
+        let too_big = file_config.max_total_size.is_some_and(|max_total_size| total_size > max_total_size);
+
+        if too_many || too_old || too_big {
+            total_size -= archive.size;
+            if let Err(err) = fs::remove_file(&archive.path) {
+                result = Err(err);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+
+    use crate::common::TestDir;
+    use crate::FileConfigBuilder;
+
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn create_file(dir: &TestDir, name: &str, size: usize, age: Duration, now: SystemTime) {
+        let mut file = File::create(dir.0.join(name)).unwrap();
+        file.write_all(&vec![b'a'; size]).unwrap();
+        file.set_modified(now - age).unwrap();
+    }
+
+    fn file_names(dir: &TestDir) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(&dir.0).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    fn prepare_dir(name: &str, now: SystemTime) -> TestDir {
+        let dir = TestDir::new(name);
+        create_file(&dir, "app.log", 10, Duration::ZERO, now);
+        create_file(&dir, "app.log.2023-07-04_20-00-00", 10, HOUR * 3, now);
+        create_file(&dir, "app.log.2023-07-04_21-00-00", 10, HOUR * 2, now);
+        create_file(&dir, "app.log.2023-07-04_21-00-00.1", 10, HOUR + HOUR / 2, now);
+        create_file(&dir, "app.log.2023-07-04_22-00-00", 10, HOUR, now);
+        create_file(&dir, "other.log", 10, HOUR * 10, now);
+        create_file(&dir, "app.log.bak", 10, HOUR * 10, now);
+        create_file(&dir, "app.log.debug", 10, HOUR * 10, now);
+        dir
+    }
+
+    #[test]
+    fn test_archive_matcher() {
+        let matcher = ArchiveMatcher::new(Path::new("logs/app.log"), "[_file_stem]-[_time]-[_index].[_file_ext]");
+        assert!(matcher.matches("app-2023-07-04_22-07-27-1.log"));
+        assert!(!matcher.matches("app--.log"));
+        assert!(!matcher.matches("app-2023-07-04_22-07-27-x.log"));
+        assert!(!matcher.matches("app-debug-1.log"));
+        assert!(!matcher.matches("app.log"));
+        assert!(!matcher.matches("app-2023-07-04_22-07-27-1.txt"));
+
+        let matcher = ArchiveMatcher::new(Path::new("app.log"), "[_file_name].[_time]");
+        assert!(matcher.matches("app.log.2023-07-04_22-07-27"));
+        assert!(matcher.matches("app.log.2023-07-04_22-07-27.2"));
+        assert!(matcher.matches("app.log.2023-07-04_22-07-27.2.gz"));
+        assert!(matcher.matches("app.log.2023-07-04_22-07-27.zst.tmp"));
+        assert!(!matcher.matches("other.log.2023-07-04_22-07-27"));
+        assert!(!matcher.matches("app.log.bak"));
+        assert!(!matcher.matches("app.log.debug"));
+        assert!(!matcher.matches("app.log"));
+
+        // Live log is excluded, even if pattern matches it
+        let matcher = ArchiveMatcher::new(Path::new("app-1.log"), "app-[_index].log");
+        assert!(matcher.matches("app-2.log"));
+        assert!(!matcher.matches("app-1.log"));
+    }
+
+    #[test]
+    fn test_max_archives() {
+        let now = SystemTime::now();
+        let dir = prepare_dir("retention_max_archives", now);
+        let file_config = FileConfigBuilder::new().set_max_archives(Some(2)).build();
+        apply_retention(&dir.0.join("app.log"), &file_config, 10, now).unwrap();
+
+        assert_eq!(file_names(&dir), vec!["app.log", "app.log.2023-07-04_21-00-00.1", "app.log.2023-07-04_22-00-00", "app.log.bak", "app.log.debug", "other.log"]);
+    }
+
+    #[test]
+    fn test_max_archive_age() {
+        let now = SystemTime::now();
+        let dir = prepare_dir("retention_max_age", now);
+        let file_config = FileConfigBuilder::new().set_max_archive_age(Some(HOUR * 2 - HOUR / 4)).build();
+        apply_retention(&dir.0.join("app.log"), &file_config, 10, now).unwrap();
+
+        assert_eq!(file_names(&dir), vec!["app.log", "app.log.2023-07-04_21-00-00.1", "app.log.2023-07-04_22-00-00", "app.log.bak", "app.log.debug", "other.log"]);
+    }
+
+    #[test]
+    fn test_max_total_size() {
+        let now = SystemTime::now();
+        let dir = prepare_dir("retention_max_total_size", now);
+        let file_config = FileConfigBuilder::new().set_max_total_size(Some(35)).build();
+        apply_retention(&dir.0.join("app.log"), &file_config, 10, now).unwrap();
+
+        assert_eq!(file_names(&dir), vec!["app.log", "app.log.2023-07-04_21-00-00.1", "app.log.2023-07-04_22-00-00", "app.log.bak", "app.log.debug", "other.log"]);
+    }
+
+    #[test]
+    fn test_no_limits_keeps_everything() {
+        let now = SystemTime::now();
+        let dir = prepare_dir("retention_no_limits", now);
+        apply_retention(&dir.0.join("app.log"), &FileConfigBuilder::new().build(), 10, now).unwrap();
+
+        assert_eq!(file_names(&dir).len(), 8);
+    }
+}
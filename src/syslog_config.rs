@@ -0,0 +1,115 @@
+use std::process;
+
+use crate::common::{app_name, hostname};
+
+/// Format of messages sent to syslog daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum SyslogFormat {
+    /// BSD syslog format e.g. `<14>Jul  4 22:07:27 host app[1234]: message`
+    #[default]
+    Rfc3164,
+    /// Newer format with year and time zone e.g. `<14>1 2023-07-04T22:07:27.123Z host app 1234 - - message`
+    Rfc5424,
+}
+
+/// Type of program that logs message, defined in RFC 5424
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Settings of `SyslogLogger` - format of messages and data identifying application
+#[derive(Debug, Clone)]
+pub struct SyslogConfig {
+    pub(crate) format: SyslogFormat,
+    pub(crate) facility: Facility,
+    pub(crate) hostname: String,
+    pub(crate) app_name: String,
+    pub(crate) pid: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyslogConfigBuilder(SyslogConfig);
+
+impl SyslogConfigBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(SyslogConfig::default())
+    }
+
+    /// Sets format of messages, by default RFC 3164 is used, which is understood by all syslog daemons
+    pub fn set_format(&mut self, format: SyslogFormat) -> &mut Self {
+        self.0.format = format;
+        self
+    }
+
+    /// Sets facility, by default `User`
+    pub fn set_facility(&mut self, facility: Facility) -> &mut Self {
+        self.0.facility = facility;
+        self
+    }
+
+    /// Sets hostname, by default name of current machine
+    pub fn set_hostname(&mut self, hostname: &str) -> &mut Self {
+        self.0.hostname = hostname.to_string();
+        self
+    }
+
+    /// Sets application name, by default name of current executable
+    pub fn set_app_name(&mut self, app_name: &str) -> &mut Self {
+        self.0.app_name = app_name.to_string();
+        self
+    }
+
+    /// Sets process id added to messages, by default id of current process
+    /// If pid is none, it is not added
+    pub fn set_pid(&mut self, pid: Option<u32>) -> &mut Self {
+        self.0.pid = pid;
+        self
+    }
+
+    /// Builds the syslog config
+    pub fn build(&mut self) -> SyslogConfig {
+        self.0.clone()
+    }
+}
+
+impl Default for SyslogConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            format: SyslogFormat::default(),
+            facility: Facility::default(),
+            hostname: hostname(),
+            app_name: app_name(),
+            pid: Some(process::id()),
+        }
+    }
+}
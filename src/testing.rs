@@ -0,0 +1,181 @@
+//! Utilities for asserting on logs in tests
+//!
+//! `capture` installs global logger, which keeps records separately for each thread, so tests
+//! running in parallel don't see records of each other
+//! ```
+//! use handsome_logger::testing;
+//! use handsome_logger::assert_logged;
+//! use log::{warn, Level};
+//!
+//! let logs = testing::capture();
+//! warn!("Disk is almost full");
+//!
+//! assert_logged!(Level::Warn, contains "almost full");
+//! assert_eq!(logs.records().len(), 1);
+//! ```
+
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+use log::{set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record};
+
+use crate::record::OwnedRecord;
+
+thread_local! {
+    static CAPTURED: RefCell<Vec<OwnedRecord>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Global logger, which saves records in buffer of thread that logged them
+struct CaptureLogger;
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let record = OwnedRecord::from_record(record);
+        // Thread local may be already destroyed when thread is exiting
+        let _ = CAPTURED.try_with(|captured| captured.borrow_mut().push(record));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs capturing logger as global logger, can be called multiple times
+/// Returns false if other global logger was already set, so records cannot be captured
+pub fn init() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(|| {
+        let installed = set_boxed_logger(Box::new(CaptureLogger)).is_ok();
+        if installed {
+            set_max_level(LevelFilter::Trace);
+        }
+        installed
+    })
+}
+
+/// Starts capturing records of current thread, records captured earlier on this thread are removed
+/// Records logged by other threads, e.g. spawned by test, are not visible here
+/// # Panics
+/// Panics if other global logger was already set
+pub fn capture() -> Captured {
+    assert!(init(), "Cannot capture logs, because other global logger was already set");
+    let captured = captured();
+    captured.clear();
+    captured
+}
+
+/// Gives access to records captured on current thread
+pub fn captured() -> Captured {
+    Captured { _not_send: std::marker::PhantomData }
+}
+
+/// Records captured on current thread
+pub struct Captured {
+    // Records are kept in thread local storage, so handle cannot be moved to other thread
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Captured {
+    /// Copies captured records, from oldest to newest
+    pub fn records(&self) -> Vec<OwnedRecord> {
+        CAPTURED.with(|captured| captured.borrow().clone())
+    }
+
+    /// Messages of captured records, from oldest to newest
+    pub fn messages(&self) -> Vec<String> {
+        CAPTURED.with(|captured| captured.borrow().iter().map(|record| record.message().to_string()).collect())
+    }
+
+    /// Checks if any record with given level contains text in its message
+    pub fn contains(&self, level: Level, text: &str) -> bool {
+        CAPTURED.with(|captured| captured.borrow().iter().any(|record| record.level() == level && record.message().contains(text)))
+    }
+
+    /// Checks if any record has given level and message
+    pub fn contains_exact(&self, level: Level, message: &str) -> bool {
+        CAPTURED.with(|captured| captured.borrow().iter().any(|record| record.level() == level && record.message() == message))
+    }
+
+    /// Removes all captured records
+    pub fn clear(&self) {
+        CAPTURED.with(|captured| captured.borrow_mut().clear());
+    }
+
+    /// Lists captured records in `[LEVEL] message` format, used in messages of failed assertions
+    pub fn describe(&self) -> String {
+        CAPTURED.with(|captured| {
+            let captured = captured.borrow();
+            if captured.is_empty() {
+                return "no records were captured".to_string();
+            }
+            captured.iter().map(|record| format!("[{}] {}", record.level(), record.message())).collect::<Vec<_>>().join("\n")
+        })
+    }
+}
+
+/// Asserts that record with given level was captured on current thread by `testing` module
+/// ```
+/// # use handsome_logger::{assert_logged, testing};
+/// # use log::{error, Level};
+/// testing::capture();
+/// error!("Connection refused");
+///
+/// assert_logged!(Level::Error, contains "refused");
+/// assert_logged!(Level::Error, "Connection refused");
+/// ```
+#[macro_export]
+macro_rules! assert_logged {
+    ($level:expr, contains $text:expr) => {{
+        let captured = $crate::testing::captured();
+        let (level, text) = ($level, $text);
+        assert!(
+            captured.contains(level, text),
+            "Expected {} record containing {:?}, captured records:\n{}",
+            level,
+            text,
+            captured.describe()
+        );
+    }};
+    ($level:expr, $message:expr) => {{
+        let captured = $crate::testing::captured();
+        let (level, message) = ($level, $message);
+        assert!(
+            captured.contains_exact(level, message),
+            "Expected {} record with message {:?}, captured records:\n{}",
+            level,
+            message,
+            captured.describe()
+        );
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use log::{info, warn};
+
+    use super::*;
+
+    #[test]
+    fn test_capture_is_per_thread() {
+        let logs = capture();
+        warn!("Disk is almost full");
+        thread::spawn(|| info!("Other thread")).join().unwrap();
+
+        assert_logged!(Level::Warn, contains "almost full");
+        assert_logged!(Level::Warn, "Disk is almost full");
+        assert_eq!(logs.messages(), ["Disk is almost full"]);
+        assert_eq!(logs.records()[0].target(), module_path!());
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected ERROR record containing \"missing\", captured records:\n[INFO] Present")]
+    fn test_failed_assertion() {
+        capture();
+        info!("Present");
+        assert_logged!(Level::Error, contains "missing");
+    }
+}